@@ -1,41 +1,68 @@
 use std::fmt::Display;
 
 use chrono::NaiveDateTime;
+use clap::ValueEnum;
 use rust_decimal::Decimal;
-use serde::Deserialize;
-
-#[derive(Debug, Deserialize)]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bill_extractor::SkippedFile,
+    error::SatrError,
+    xml_extractor::{DocumentKind, Origin, XmlSource},
+};
+use complement::Complement;
+use local_taxes::LocalTaxes;
+use payroll::Payroll;
+
+pub mod complement;
+pub mod local_taxes;
+pub mod payments;
+pub mod payroll;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Bill {
     #[serde(rename = "@Version")]
-    pub version: String,
+    pub version: CfdiVersion,
 
     #[serde(rename = "@Fecha")]
     pub date: NaiveDateTime,
 
     #[serde(rename = "@FormaPago")]
-    pub pay_form: String,
+    pub pay_form: Option<String>,
 
     #[serde(rename = "@SubTotal")]
     pub subtotal: Decimal,
 
+    #[serde(rename = "@Descuento")]
+    pub discount: Option<Decimal>,
+
     #[serde(rename = "@Moneda")]
     pub currency: String,
 
+    /// Obligatorio cuando la moneda es distinta de MXN y XXX.
+    #[serde(rename = "@TipoCambio")]
+    pub exchange_rate: Option<Decimal>,
+
     #[serde(rename = "@Total")]
     pub total: Decimal,
 
     #[serde(rename = "@TipoDeComprobante")]
-    pub receipt_type: String,
+    pub receipt_type: ReceiptType,
 
+    /// Solo CFDI 4.0.
     #[serde(rename = "@Exportacion")]
-    pub exportation: String,
+    pub exportation: Option<String>,
 
     #[serde(rename = "@MetodoPago")]
-    pub pay_method: String,
+    pub pay_method: Option<String>,
 
     #[serde(rename = "@LugarExpedicion")]
     pub expedition_place: String,
 
+    /// CFDI 3.3 admite un solo nodo, CFDI 4.0 uno por tipo de relación.
+    #[serde(rename = "CfdiRelacionados", default)]
+    pub relations: Vec<Relations>,
+
     #[serde(rename = "Emisor")]
     pub emisor: Emisor,
 
@@ -45,87 +72,294 @@ pub struct Bill {
     #[serde(rename = "Conceptos")]
     pub concepts: Concepts,
 
-    #[serde(rename = "Impuestos")]
+    #[serde(rename = "Impuestos", default)]
     pub taxes: Taxes,
+
+    #[serde(rename = "Complemento", default)]
+    pub complement: Complement,
+
+    /// Archivo del que se extrajo la factura.
+    #[serde(skip)]
+    pub source: Option<Origin>,
 }
 
 impl Bill {
+    /// Deserializa un CFDI y valida los atributos obligatorios de su versión.
+    pub fn from_xml(xml: &str) -> Result<Self, SatrError> {
+        let bill: Bill = quick_xml::de::from_str(xml)?;
+        bill.validate_version()?;
+
+        Ok(bill)
+    }
+
+    /// Deserializa un CFDI conservando el archivo del que proviene.
+    pub fn from_source(source: XmlSource) -> Result<Self, SkippedFile> {
+        if source.root().kind != DocumentKind::Cfdi {
+            return Err(SkippedFile {
+                origin: source.origin,
+                error: SatrError::NotCfdi,
+            });
+        }
+
+        let XmlSource { origin, contents } = source;
+
+        match Self::from_xml(&contents) {
+            Ok(mut bill) => {
+                bill.source = Some(origin);
+                Ok(bill)
+            }
+            Err(error) => Err(SkippedFile { origin, error }),
+        }
+    }
+
+    fn validate_version(&self) -> Result<(), SatrError> {
+        match self.version {
+            CfdiVersion::V33 => Ok(()),
+            CfdiVersion::V40 => {
+                let missing = [
+                    ("Exportacion", self.exportation.is_none()),
+                    ("Emisor@Nombre", self.emisor.name.is_none()),
+                    ("Receptor@Nombre", self.recipient.name.is_none()),
+                    ("DomicilioFiscalReceptor", self.recipient.zip_code.is_none()),
+                    (
+                        "RegimenFiscalReceptor",
+                        self.recipient.fiscal_regiment.is_none(),
+                    ),
+                ]
+                .into_iter()
+                .find(|(_, missing)| *missing);
+
+                match missing {
+                    Some((attr, _)) => Err(SatrError::InvalidBill(format!(
+                        "CFDI 4.0 sin el atributo {attr}"
+                    ))),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Folio fiscal asignado por el timbre fiscal digital.
+    #[inline]
+    pub fn uuid(&self) -> Option<&str> {
+        self.complement.stamp.as_ref().map(|s| s.uuid.as_str())
+    }
+
+    /// UUID de los comprobantes relacionados, junto con el tipo de relación.
+    pub fn related(&self) -> impl Iterator<Item = (RelationType, &str)> {
+        self.relations
+            .iter()
+            .flat_map(|r| r.list.iter().map(|b| (r.relation_type, b.uuid.as_str())))
+    }
+
+    /// Factura con método de pago en parcialidades o diferido (PPD).
+    #[inline]
+    pub fn is_deferred(&self) -> bool {
+        self.pay_method.as_deref() == Some("PPD")
+    }
+
+    /// Tipo de cambio para convertir los importes de la factura a MXN.
+    pub fn exchange_rate(&self) -> Result<Decimal, SatrError> {
+        if matches!(self.currency.as_str(), "MXN" | "XXX") {
+            return Ok(Decimal::ONE);
+        }
+
+        self.exchange_rate
+            .ok_or_else(|| SatrError::MissingExchangeRate {
+                uuid: self.uuid().unwrap_or("sin timbrar").to_string(),
+                currency: self.currency.clone(),
+            })
+    }
+
     #[inline]
     pub fn total(&self) -> Decimal {
         self.total
     }
 
+    /// Subtotal menos descuentos.
     #[inline]
     pub fn subtotal(&self) -> Decimal {
-        self.subtotal
+        self.subtotal - self.discount()
     }
 
-    pub fn iva(&self) -> Decimal {
-        let mut total = Decimal::ZERO;
-        if let Some(w) = &self.taxes.withheld {
-            total += w.iva()
-        }
+    #[inline]
+    pub fn discount(&self) -> Decimal {
+        self.discount.unwrap_or_default()
+    }
 
-        if let Some(c) = &self.taxes.carried_forward {
-            total += c.iva()
-        }
+    /// Total según la fórmula del SAT: subtotal - descuento + trasladados - retenidos,
+    /// incluyendo los impuestos locales.
+    pub fn computed_total(&self) -> Decimal {
+        self.subtotal() + self.taxes.carried_forward_total() - self.taxes.withheld_total()
+            + self.local_taxes_carried_forward()
+            - self.local_taxes_withheld()
+    }
 
-        total
+    /// Indica si el total declarado coincide con el calculado, tolerando diferencias de
+    /// redondeo de un centavo.
+    pub fn reconciles(&self) -> bool {
+        (self.total - self.computed_total()).abs() <= Decimal::new(1, 2)
     }
 
-    pub fn isr(&self) -> Decimal {
-        let mut total = Decimal::ZERO;
-        if let Some(w) = &self.taxes.withheld {
-            total += w.isr()
-        }
+    /// Percepciones totales de los complementos de nómina, cero si no tiene.
+    pub fn gross_salary(&self) -> Decimal {
+        self.complement.payroll.iter().map(Payroll::gross).sum()
+    }
 
-        if let Some(c) = &self.taxes.carried_forward {
-            total += c.isr()
-        }
+    pub fn iva_carried_forward(&self) -> Decimal {
+        self.taxes
+            .carried_forward
+            .as_ref()
+            .map(CarriedForward::iva)
+            .unwrap_or_default()
+    }
+
+    pub fn iva_withheld(&self) -> Decimal {
+        self.taxes
+            .withheld
+            .as_ref()
+            .map(Withheld::iva)
+            .unwrap_or_default()
+    }
+
+    /// IVA trasladado menos retenido.
+    #[inline]
+    pub fn iva_net(&self) -> Decimal {
+        self.iva_carried_forward() - self.iva_withheld()
+    }
+
+    pub fn isr_carried_forward(&self) -> Decimal {
+        self.taxes
+            .carried_forward
+            .as_ref()
+            .map(CarriedForward::isr)
+            .unwrap_or_default()
+    }
+
+    pub fn isr_withheld(&self) -> Decimal {
+        self.taxes
+            .withheld
+            .as_ref()
+            .map(Withheld::isr)
+            .unwrap_or_default()
+    }
 
-        total
+    /// ISR trasladado menos retenido.
+    #[inline]
+    pub fn isr_net(&self) -> Decimal {
+        self.isr_carried_forward() - self.isr_withheld()
+    }
+
+    pub fn ieps_carried_forward(&self) -> Decimal {
+        self.taxes
+            .carried_forward
+            .as_ref()
+            .map(CarriedForward::ieps)
+            .unwrap_or_default()
     }
+
+    pub fn ieps_withheld(&self) -> Decimal {
+        self.taxes
+            .withheld
+            .as_ref()
+            .map(Withheld::ieps)
+            .unwrap_or_default()
+    }
+
+    /// IEPS trasladado menos retenido.
+    #[inline]
+    pub fn ieps_net(&self) -> Decimal {
+        self.ieps_carried_forward() - self.ieps_withheld()
+    }
+
+    pub fn local_taxes_carried_forward(&self) -> Decimal {
+        self.complement
+            .local_taxes
+            .as_ref()
+            .map(LocalTaxes::carried_forward_total)
+            .unwrap_or_default()
+    }
+
+    pub fn local_taxes_withheld(&self) -> Decimal {
+        self.complement
+            .local_taxes
+            .as_ref()
+            .map(LocalTaxes::withheld_total)
+            .unwrap_or_default()
+    }
+
+    /// Impuestos locales trasladados menos retenidos.
+    #[inline]
+    pub fn local_taxes(&self) -> Decimal {
+        self.local_taxes_carried_forward() - self.local_taxes_withheld()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Relations {
+    #[serde(rename = "@TipoRelacion")]
+    pub relation_type: RelationType,
+
+    #[serde(rename = "CfdiRelacionado")]
+    pub list: Vec<RelatedBill>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedBill {
+    #[serde(rename = "@UUID")]
+    pub uuid: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Emisor {
     #[serde(rename = "@Rfc")]
     pub rfc: String,
+    /// Opcional en CFDI 3.3.
     #[serde(rename = "@Nombre")]
-    pub name: String,
+    pub name: Option<String>,
     #[serde(rename = "@RegimenFiscal")]
     pub fiscal_regiment: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Recipient {
     #[serde(rename = "@Rfc")]
     pub rfc: String,
 
+    /// Opcional en CFDI 3.3.
     #[serde(rename = "@Nombre")]
-    pub name: String,
+    pub name: Option<String>,
 
+    /// Solo CFDI 4.0.
     #[serde(rename = "@DomicilioFiscalReceptor")]
-    pub zip_code: String,
+    pub zip_code: Option<String>,
 
+    /// Solo CFDI 4.0.
     #[serde(rename = "@RegimenFiscalReceptor")]
-    pub fiscal_regiment: String,
+    pub fiscal_regiment: Option<String>,
+
+    /// Solo para receptores extranjeros.
+    #[serde(rename = "@ResidenciaFiscal")]
+    pub fiscal_residence: Option<String>,
 
     #[serde(rename = "@UsoCFDI")]
     pub cfdi_use: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Concepts {
     #[serde(rename = "Concepto")]
     pub list: Vec<Concept>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Concept {
     #[serde(rename = "@ClaveProdServ")]
     pub key: String,
 
+    #[serde(rename = "@NoIdentificacion")]
+    pub id_number: Option<String>,
+
     #[serde(rename = "@Cantidad")]
     pub quantity: Decimal,
 
@@ -133,7 +367,7 @@ pub struct Concept {
     pub unity_key: String,
 
     #[serde(rename = "@Unidad")]
-    pub unity: String,
+    pub unity: Option<String>,
 
     #[serde(rename = "@Descripcion")]
     pub description: String,
@@ -143,9 +377,27 @@ pub struct Concept {
 
     #[serde(rename = "@Importe")]
     pub value: Decimal,
+
+    #[serde(rename = "@Descuento")]
+    pub discount: Option<Decimal>,
+
+    /// Solo CFDI 4.0.
+    #[serde(rename = "@ObjetoImp")]
+    pub tax_object: Option<String>,
+
+    #[serde(rename = "Impuestos", default)]
+    pub taxes: Taxes,
 }
 
-#[derive(Debug, Deserialize)]
+impl Concept {
+    /// Importe menos descuento.
+    #[inline]
+    pub fn net_value(&self) -> Decimal {
+        self.value - self.discount.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
 pub struct Taxes {
     #[serde(rename = "Retenciones")]
     pub withheld: Option<Withheld>,
@@ -154,7 +406,25 @@ pub struct Taxes {
     pub carried_forward: Option<CarriedForward>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Taxes {
+    /// Suma de todos los impuestos trasladados.
+    pub fn carried_forward_total(&self) -> Decimal {
+        self.carried_forward
+            .as_ref()
+            .map(|c| c.taxes.iter().map(Tax::value).sum())
+            .unwrap_or_default()
+    }
+
+    /// Suma de todos los impuestos retenidos.
+    pub fn withheld_total(&self) -> Decimal {
+        self.withheld
+            .as_ref()
+            .map(|w| w.taxes.iter().map(Tax::value).sum())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Withheld {
     #[serde(rename = "Retencion")]
     pub taxes: Vec<Tax>,
@@ -172,9 +442,13 @@ impl Withheld {
     pub fn isr(&self) -> Decimal {
         self.tax_sum(TaxType::Isr)
     }
+
+    pub fn ieps(&self) -> Decimal {
+        self.tax_sum(TaxType::Ieps)
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct CarriedForward {
     #[serde(rename = "Traslado")]
     pub taxes: Vec<Tax>,
@@ -192,15 +466,40 @@ impl CarriedForward {
     pub fn isr(&self) -> Decimal {
         self.tax_sum(TaxType::Isr)
     }
+
+    pub fn ieps(&self) -> Decimal {
+        self.tax_sum(TaxType::Ieps)
+    }
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
 pub struct Tax {
-    #[serde(rename = "@Impuesto")]
+    /// No se incluye en las retenciones del comprobante, ni en los traslados de CFDI 3.3.
+    #[serde(rename = "@Base", alias = "@BaseDR", alias = "@BaseP")]
+    pub base: Option<Decimal>,
+
+    #[serde(rename = "@Impuesto", alias = "@ImpuestoDR", alias = "@ImpuestoP")]
     pub ty: TaxType,
 
-    #[serde(rename = "@Importe")]
-    pub value: Decimal,
+    /// No se incluye en las retenciones del comprobante.
+    #[serde(
+        rename = "@TipoFactor",
+        alias = "@TipoFactorDR",
+        alias = "@TipoFactorP"
+    )]
+    pub factor_type: Option<FactorType>,
+
+    /// No se incluye en los traslados exentos.
+    #[serde(
+        rename = "@TasaOCuota",
+        alias = "@TasaOCuotaDR",
+        alias = "@TasaOCuotaP"
+    )]
+    pub rate: Option<Decimal>,
+
+    /// No se incluye en los traslados exentos.
+    #[serde(rename = "@Importe", alias = "@ImporteDR", alias = "@ImporteP")]
+    pub value: Option<Decimal>,
 }
 
 impl Tax {
@@ -208,12 +507,179 @@ impl Tax {
     pub fn sum_iter<'a, I: Iterator<Item = &'a Tax>>(it: I, ty: TaxType) -> Decimal {
         it.fold(Decimal::ZERO, |acc, tax| {
             if tax.ty == ty {
-                acc + tax.value
+                acc + tax.value()
             } else {
                 acc
             }
         })
     }
+
+    #[inline]
+    pub fn value(&self) -> Decimal {
+        self.value.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(try_from = "String")]
+pub enum CfdiVersion {
+    /// 3.3
+    V33,
+    /// 4.0
+    V40,
+}
+
+impl Display for CfdiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CfdiVersion::V33 => write!(f, "3.3"),
+            CfdiVersion::V40 => write!(f, "4.0"),
+        }
+    }
+}
+
+impl TryFrom<String> for CfdiVersion {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "3.3" => Ok(Self::V33),
+            "4.0" => Ok(Self::V40),
+            _ => Err("Versión de CFDI no soportada"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, ValueEnum)]
+#[serde(try_from = "String")]
+pub enum ReceiptType {
+    /// I
+    #[value(alias = "I")]
+    Ingreso,
+    /// E
+    #[value(alias = "E")]
+    Egreso,
+    /// T
+    #[value(alias = "T")]
+    Traslado,
+    /// P
+    #[value(alias = "P")]
+    Pago,
+    /// N
+    #[value(alias = "N")]
+    Nomina,
+}
+
+impl ReceiptType {
+    /// Signo con el que el comprobante se acumula en los reportes, los traslados y
+    /// complementos de pago no representan montos propios, por lo que se excluyen. Los
+    /// recibos de nómina solo se acumulan en el reporte de nómina.
+    pub fn sign(&self) -> Option<Decimal> {
+        match self {
+            ReceiptType::Ingreso => Some(Decimal::ONE),
+            ReceiptType::Egreso => Some(Decimal::NEGATIVE_ONE),
+            ReceiptType::Traslado | ReceiptType::Pago | ReceiptType::Nomina => None,
+        }
+    }
+
+    /// Signo con el que el comprobante se acumula en el reporte de nómina.
+    pub fn payroll_sign(&self) -> Option<Decimal> {
+        match self {
+            ReceiptType::Nomina => Some(Decimal::ONE),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ReceiptType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReceiptType::Ingreso => write!(f, "I"),
+            ReceiptType::Egreso => write!(f, "E"),
+            ReceiptType::Traslado => write!(f, "T"),
+            ReceiptType::Pago => write!(f, "P"),
+            ReceiptType::Nomina => write!(f, "N"),
+        }
+    }
+}
+
+impl TryFrom<String> for ReceiptType {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "I" => Ok(Self::Ingreso),
+            "E" => Ok(Self::Egreso),
+            "T" => Ok(Self::Traslado),
+            "P" => Ok(Self::Pago),
+            "N" => Ok(Self::Nomina),
+            _ => Err("Tipo de comprobante no soportado"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(try_from = "String")]
+pub enum RelationType {
+    /// 01
+    NotaCredito,
+    /// 02
+    NotaDebito,
+    /// 03
+    Devolucion,
+    /// 04
+    Sustitucion,
+    /// 05
+    TrasladosPrevios,
+    /// 06
+    FacturaTraslados,
+    /// 07
+    Anticipo,
+}
+
+impl RelationType {
+    pub fn description(&self) -> &'static str {
+        match self {
+            RelationType::NotaCredito => "Nota de crédito",
+            RelationType::NotaDebito => "Nota de débito",
+            RelationType::Devolucion => "Devolución de mercancía",
+            RelationType::Sustitucion => "Sustitución de CFDI previos",
+            RelationType::TrasladosPrevios => "Traslados de mercancías facturados previamente",
+            RelationType::FacturaTraslados => "Factura generada por los traslados previos",
+            RelationType::Anticipo => "Aplicación de anticipo",
+        }
+    }
+}
+
+impl Display for RelationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelationType::NotaCredito => write!(f, "01"),
+            RelationType::NotaDebito => write!(f, "02"),
+            RelationType::Devolucion => write!(f, "03"),
+            RelationType::Sustitucion => write!(f, "04"),
+            RelationType::TrasladosPrevios => write!(f, "05"),
+            RelationType::FacturaTraslados => write!(f, "06"),
+            RelationType::Anticipo => write!(f, "07"),
+        }
+    }
+}
+
+impl TryFrom<String> for RelationType {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "01" => Ok(Self::NotaCredito),
+            "02" => Ok(Self::NotaDebito),
+            "03" => Ok(Self::Devolucion),
+            "04" => Ok(Self::Sustitucion),
+            "05" => Ok(Self::TrasladosPrevios),
+            "06" => Ok(Self::FacturaTraslados),
+            "07" => Ok(Self::Anticipo),
+            _ => Err("Tipo de relación no soportado"),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
@@ -224,6 +690,8 @@ pub enum TaxType {
     Isr,
     /// 002
     Iva,
+    /// 003
+    Ieps,
 }
 
 impl Display for TaxType {
@@ -231,6 +699,7 @@ impl Display for TaxType {
         match self {
             TaxType::Iva => write!(f, "002"),
             TaxType::Isr => write!(f, "001"),
+            TaxType::Ieps => write!(f, "003"),
         }
     }
 }
@@ -242,7 +711,41 @@ impl TryFrom<String> for TaxType {
         match s.as_str() {
             "001" => Ok(Self::Isr),
             "002" => Ok(Self::Iva),
+            "003" => Ok(Self::Ieps),
             _ => Err("Impuesto no soportado"),
         }
     }
 }
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(try_from = "String")]
+pub enum FactorType {
+    Tasa,
+    Cuota,
+    Exento,
+}
+
+impl Display for FactorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FactorType::Tasa => write!(f, "Tasa"),
+            FactorType::Cuota => write!(f, "Cuota"),
+            FactorType::Exento => write!(f, "Exento"),
+        }
+    }
+}
+
+impl TryFrom<String> for FactorType {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "Tasa" => Ok(Self::Tasa),
+            "Cuota" => Ok(Self::Cuota),
+            "Exento" => Ok(Self::Exento),
+            _ => Err("Tipo de factor no soportado"),
+        }
+    }
+}
+
+crate::serialize_display!(CfdiVersion, ReceiptType, RelationType, TaxType, FactorType);
@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{local_taxes::LocalTaxes, payments::Payments, payroll::Payroll};
+
+/// Nodo `cfdi:Complemento`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
+pub struct Complement {
+    #[serde(rename = "TimbreFiscalDigital")]
+    pub stamp: Option<DigitalStamp>,
+
+    #[serde(rename = "Pagos")]
+    pub payments: Option<Payments>,
+
+    /// Un recibo puede incluir varios complementos, por ejemplo uno de nómina ordinaria y
+    /// otro de extraordinaria.
+    #[serde(rename = "Nomina", default)]
+    pub payroll: Vec<Payroll>,
+
+    #[serde(rename = "ImpuestosLocales")]
+    pub local_taxes: Option<LocalTaxes>,
+}
+
+/// Complemento `tfd:TimbreFiscalDigital`, agregado por el PAC al certificar el CFDI.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DigitalStamp {
+    #[serde(rename = "@Version")]
+    pub version: String,
+
+    #[serde(rename = "@UUID")]
+    pub uuid: String,
+
+    #[serde(rename = "@FechaTimbrado")]
+    pub stamp_date: NaiveDateTime,
+
+    #[serde(rename = "@RfcProvCertif")]
+    pub provider_rfc: String,
+
+    #[serde(rename = "@SelloCFD")]
+    pub cfd_seal: String,
+
+    #[serde(rename = "@NoCertificadoSAT")]
+    pub sat_certificate_number: String,
+
+    #[serde(rename = "@SelloSAT")]
+    pub sat_seal: String,
+}
@@ -0,0 +1,60 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Complemento `implocal:ImpuestosLocales`, con impuestos estatales o municipales como el de
+/// hospedaje, nóminas o cedulares.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct LocalTaxes {
+    #[serde(rename = "@version")]
+    pub version: String,
+
+    #[serde(rename = "@TotaldeRetenciones")]
+    pub total_withheld: Decimal,
+
+    #[serde(rename = "@TotaldeTraslados")]
+    pub total_carried_forward: Decimal,
+
+    #[serde(rename = "RetencionesLocales", default)]
+    pub withheld: Vec<LocalWithheld>,
+
+    #[serde(rename = "TrasladosLocales", default)]
+    pub carried_forward: Vec<LocalCarriedForward>,
+}
+
+impl LocalTaxes {
+    pub fn withheld_total(&self) -> Decimal {
+        self.withheld.iter().map(|t| t.value).sum()
+    }
+
+    pub fn carried_forward_total(&self) -> Decimal {
+        self.carried_forward.iter().map(|t| t.value).sum()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct LocalWithheld {
+    /// Nombre del impuesto, sin catálogo.
+    #[serde(rename = "@ImpLocRetenido")]
+    pub name: String,
+
+    /// Porcentaje, por ejemplo 3.00 para el 3%.
+    #[serde(rename = "@TasadeRetencion")]
+    pub rate: Decimal,
+
+    #[serde(rename = "@Importe")]
+    pub value: Decimal,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct LocalCarriedForward {
+    /// Nombre del impuesto, sin catálogo.
+    #[serde(rename = "@ImpLocTrasladado")]
+    pub name: String,
+
+    /// Porcentaje, por ejemplo 3.00 para el 3%.
+    #[serde(rename = "@TasadeTraslado")]
+    pub rate: Decimal,
+
+    #[serde(rename = "@Importe")]
+    pub value: Decimal,
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::Tax;
+
+/// Complemento `pago20:Pagos` o `pago10:Pagos`, emitido en comprobantes de tipo P.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Payments {
+    #[serde(rename = "@Version")]
+    pub version: String,
+
+    #[serde(rename = "Totales")]
+    pub totals: Option<PaymentTotals>,
+
+    #[serde(rename = "Pago")]
+    pub list: Vec<Payment>,
+}
+
+impl Payments {
+    /// Documentos relacionados de todos los pagos, junto con el pago al que pertenecen.
+    pub fn related_documents(&self) -> impl Iterator<Item = (&Payment, &RelatedDocument)> {
+        self.list
+            .iter()
+            .flat_map(|p| p.related_documents.iter().map(move |d| (p, d)))
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentTotals {
+    #[serde(rename = "@MontoTotalPagos")]
+    pub total: Decimal,
+
+    #[serde(rename = "@TotalRetencionesIVA")]
+    pub iva_withheld: Option<Decimal>,
+
+    #[serde(rename = "@TotalRetencionesISR")]
+    pub isr_withheld: Option<Decimal>,
+
+    #[serde(rename = "@TotalRetencionesIEPS")]
+    pub ieps_withheld: Option<Decimal>,
+
+    #[serde(rename = "@TotalTrasladosBaseIVA16")]
+    pub iva_16_base: Option<Decimal>,
+
+    #[serde(rename = "@TotalTrasladosImpuestoIVA16")]
+    pub iva_16: Option<Decimal>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Payment {
+    #[serde(rename = "@FechaPago")]
+    pub date: NaiveDateTime,
+
+    #[serde(rename = "@FormaDePagoP")]
+    pub pay_form: String,
+
+    #[serde(rename = "@MonedaP")]
+    pub currency: String,
+
+    #[serde(rename = "@TipoCambioP")]
+    pub exchange_rate: Option<Decimal>,
+
+    #[serde(rename = "@Monto")]
+    pub amount: Decimal,
+
+    #[serde(rename = "DoctoRelacionado", default)]
+    pub related_documents: Vec<RelatedDocument>,
+
+    #[serde(rename = "ImpuestosP")]
+    pub taxes: Option<PaymentTaxes>,
+}
+
+impl Payment {
+    /// Tipo de cambio a MXN de la moneda de un documento relacionado en la fecha del pago:
+    /// `TipoCambioP` entre `EquivalenciaDR`. `None` si el pago en moneda extranjera no
+    /// tiene tipo de cambio.
+    pub fn exchange_rate_for(&self, document: &RelatedDocument) -> Option<Decimal> {
+        if matches!(document.currency.as_str(), "MXN" | "XXX") {
+            return Some(Decimal::ONE);
+        }
+
+        let rate = match self.currency.as_str() {
+            "MXN" => Decimal::ONE,
+            _ => self.exchange_rate?,
+        };
+        let equivalence = document.equivalence.unwrap_or(Decimal::ONE);
+        if equivalence.is_zero() {
+            return None;
+        }
+
+        Some(rate / equivalence)
+    }
+}
+
+/// Factura PPD a la que se aplica un pago.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedDocument {
+    /// UUID de la factura pagada.
+    #[serde(rename = "@IdDocumento")]
+    pub uuid: String,
+
+    #[serde(rename = "@Serie")]
+    pub serie: Option<String>,
+
+    #[serde(rename = "@Folio")]
+    pub folio: Option<String>,
+
+    #[serde(rename = "@MonedaDR")]
+    pub currency: String,
+
+    /// `EquivalenciaDR` en Pagos 2.0, `TipoCambioDR` en Pagos 1.0 con el mismo significado.
+    #[serde(rename = "@EquivalenciaDR", alias = "@TipoCambioDR")]
+    pub equivalence: Option<Decimal>,
+
+    #[serde(rename = "@NumParcialidad")]
+    pub installment: Option<u32>,
+
+    #[serde(rename = "@ImpSaldoAnt")]
+    pub previous_balance: Option<Decimal>,
+
+    #[serde(rename = "@ImpPagado")]
+    pub paid: Option<Decimal>,
+
+    #[serde(rename = "@ImpSaldoInsoluto")]
+    pub outstanding_balance: Option<Decimal>,
+
+    #[serde(rename = "@ObjetoImpDR")]
+    pub tax_object: Option<String>,
+
+    #[serde(rename = "ImpuestosDR")]
+    pub taxes: Option<RelatedDocumentTaxes>,
+}
+
+impl RelatedDocument {
+    #[inline]
+    pub fn paid(&self) -> Decimal {
+        self.paid.unwrap_or_default()
+    }
+}
+
+/// Los nodos de impuestos de los pagos usan los sufijos `DR` y `P` en sus atributos,
+/// que se deserializan como alias de [`Tax`].
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedDocumentTaxes {
+    #[serde(rename = "RetencionesDR")]
+    pub withheld: Option<RelatedDocumentWithheld>,
+
+    #[serde(rename = "TrasladosDR")]
+    pub carried_forward: Option<RelatedDocumentCarriedForward>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedDocumentWithheld {
+    #[serde(rename = "RetencionDR")]
+    pub taxes: Vec<Tax>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedDocumentCarriedForward {
+    #[serde(rename = "TrasladoDR")]
+    pub taxes: Vec<Tax>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentTaxes {
+    #[serde(rename = "RetencionesP")]
+    pub withheld: Option<PaymentWithheld>,
+
+    #[serde(rename = "TrasladosP")]
+    pub carried_forward: Option<PaymentCarriedForward>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentWithheld {
+    #[serde(rename = "RetencionP")]
+    pub taxes: Vec<Tax>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentCarriedForward {
+    #[serde(rename = "TrasladoP")]
+    pub taxes: Vec<Tax>,
+}
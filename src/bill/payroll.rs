@@ -0,0 +1,223 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Tipo de deducción del catálogo del SAT correspondiente al ISR retenido.
+const ISR_DEDUCTION: &str = "002";
+
+/// Complemento `nomina12:Nomina`, emitido en comprobantes de tipo N.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Payroll {
+    #[serde(rename = "@Version")]
+    pub version: String,
+
+    /// O ordinaria, E extraordinaria.
+    #[serde(rename = "@TipoNomina")]
+    pub payroll_type: String,
+
+    #[serde(rename = "@FechaPago")]
+    pub pay_date: NaiveDate,
+
+    #[serde(rename = "@FechaInicialPago")]
+    pub start_date: NaiveDate,
+
+    #[serde(rename = "@FechaFinalPago")]
+    pub end_date: NaiveDate,
+
+    #[serde(rename = "@NumDiasPagados")]
+    pub paid_days: Decimal,
+
+    #[serde(rename = "@TotalPercepciones")]
+    pub total_perceptions: Option<Decimal>,
+
+    #[serde(rename = "@TotalDeducciones")]
+    pub total_deductions: Option<Decimal>,
+
+    #[serde(rename = "@TotalOtrosPagos")]
+    pub total_other_payments: Option<Decimal>,
+
+    #[serde(rename = "Emisor")]
+    pub employer: Option<PayrollEmployer>,
+
+    #[serde(rename = "Receptor")]
+    pub employee: PayrollEmployee,
+
+    #[serde(rename = "Percepciones")]
+    pub perceptions: Option<Perceptions>,
+
+    #[serde(rename = "Deducciones")]
+    pub deductions: Option<Deductions>,
+
+    #[serde(rename = "OtrosPagos")]
+    pub other_payments: Option<OtherPayments>,
+}
+
+impl Payroll {
+    /// Percepciones gravadas más exentas.
+    pub fn gross(&self) -> Decimal {
+        self.taxed() + self.exempt()
+    }
+
+    pub fn taxed(&self) -> Decimal {
+        self.perceptions
+            .as_ref()
+            .map(|p| p.taxed)
+            .unwrap_or_default()
+    }
+
+    pub fn exempt(&self) -> Decimal {
+        self.perceptions
+            .as_ref()
+            .map(|p| p.exempt)
+            .unwrap_or_default()
+    }
+
+    /// ISR retenido al trabajador, deducción de tipo 002.
+    pub fn isr_withheld(&self) -> Decimal {
+        self.deductions
+            .as_ref()
+            .map(|d| {
+                d.list
+                    .iter()
+                    .filter(|d| d.ty == ISR_DEDUCTION)
+                    .map(|d| d.value)
+                    .sum()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Subsidio para el empleo causado en el periodo.
+    pub fn employment_subsidy(&self) -> Decimal {
+        self.other_payments
+            .as_ref()
+            .map(|o| {
+                o.list
+                    .iter()
+                    .filter_map(|p| p.employment_subsidy.as_ref())
+                    .map(|s| s.caused)
+                    .sum()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PayrollEmployer {
+    /// Solo cuando el patrón está registrado ante el IMSS.
+    #[serde(rename = "@RegistroPatronal")]
+    pub employer_registration: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PayrollEmployee {
+    #[serde(rename = "@Curp")]
+    pub curp: String,
+
+    #[serde(rename = "@NumEmpleado")]
+    pub employee_number: String,
+
+    #[serde(rename = "@TipoContrato")]
+    pub contract_type: String,
+
+    #[serde(rename = "@PeriodicidadPago")]
+    pub pay_frequency: String,
+
+    #[serde(rename = "@SalarioDiarioIntegrado")]
+    pub integrated_daily_salary: Option<Decimal>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Perceptions {
+    #[serde(rename = "@TotalSueldos")]
+    pub salaries: Option<Decimal>,
+
+    #[serde(rename = "@TotalSeparacionIndemnizacion")]
+    pub severance: Option<Decimal>,
+
+    #[serde(rename = "@TotalJubilacionPensionRetiro")]
+    pub retirement: Option<Decimal>,
+
+    #[serde(rename = "@TotalGravado")]
+    pub taxed: Decimal,
+
+    #[serde(rename = "@TotalExento")]
+    pub exempt: Decimal,
+
+    #[serde(rename = "Percepcion")]
+    pub list: Vec<Perception>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Perception {
+    #[serde(rename = "@TipoPercepcion")]
+    pub ty: String,
+
+    #[serde(rename = "@Clave")]
+    pub key: String,
+
+    #[serde(rename = "@Concepto")]
+    pub description: String,
+
+    #[serde(rename = "@ImporteGravado")]
+    pub taxed: Decimal,
+
+    #[serde(rename = "@ImporteExento")]
+    pub exempt: Decimal,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Deductions {
+    #[serde(rename = "@TotalOtrasDeducciones")]
+    pub other_deductions: Option<Decimal>,
+
+    #[serde(rename = "@TotalImpuestosRetenidos")]
+    pub taxes_withheld: Option<Decimal>,
+
+    #[serde(rename = "Deduccion")]
+    pub list: Vec<Deduction>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Deduction {
+    #[serde(rename = "@TipoDeduccion")]
+    pub ty: String,
+
+    #[serde(rename = "@Clave")]
+    pub key: String,
+
+    #[serde(rename = "@Concepto")]
+    pub description: String,
+
+    #[serde(rename = "@Importe")]
+    pub value: Decimal,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct OtherPayments {
+    #[serde(rename = "OtroPago")]
+    pub list: Vec<OtherPayment>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct OtherPayment {
+    #[serde(rename = "@TipoOtroPago")]
+    pub ty: String,
+
+    #[serde(rename = "@Clave")]
+    pub key: String,
+
+    #[serde(rename = "@Concepto")]
+    pub description: String,
+
+    #[serde(rename = "@Importe")]
+    pub value: Decimal,
+
+    #[serde(rename = "SubsidioAlEmpleo")]
+    pub employment_subsidy: Option<EmploymentSubsidy>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EmploymentSubsidy {
+    #[serde(rename = "@SubsidioCausado")]
+    pub caused: Decimal,
+}
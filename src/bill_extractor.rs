@@ -1,8 +1,16 @@
-use std::{borrow::Cow, path::Path};
+use std::{borrow::Cow, collections::HashSet, path::Path, sync::Arc};
 
 use chrono::{Local, NaiveDate, NaiveDateTime};
 
-use crate::{bill::Bill, cli::report::SubjectType, xml_extractor::XmlExtractor};
+use crate::{
+    bill::{Bill, ReceiptType},
+    cancellations::CancellationList,
+    cli::report::SubjectType,
+    error::SatrError,
+    index::Index,
+    parallel::{self, OrderedMap},
+    xml_extractor::{DocumentKind, Origin, XmlExtractor, XmlSource},
+};
 
 #[derive(Debug, Clone)]
 pub struct BillExtractor<'a> {
@@ -16,15 +24,32 @@ impl<'a> BillExtractor<'a> {
 
     #[inline]
     fn rfc_match(&self, bill: &Bill) -> bool {
+        let Some(rfc) = &self.config.subject_rfc else {
+            return true;
+        };
+
         match self.config.subject_type {
-            SubjectType::Emisor => bill.emisor.rfc == self.config.subject_rfc,
-            SubjectType::Receptor => bill.recipient.rfc == self.config.subject_rfc,
+            SubjectType::Emisor => &bill.emisor.rfc == rfc,
+            SubjectType::Receptor => &bill.recipient.rfc == rfc,
         }
     }
 
     #[inline]
     fn dates_match(&self, bill: &Bill) -> bool {
-        bill.date >= self.config.dates.date_start && bill.date <= self.config.dates.date_end
+        self.config.dates.contains(bill.date)
+    }
+
+    #[inline]
+    fn receipt_type_match(&self, bill: &Bill) -> bool {
+        self.config.receipt_type_match(bill)
+    }
+
+    #[inline]
+    fn is_cancelled(&self, bill: &Bill) -> bool {
+        match (&self.config.cancellations, bill.uuid()) {
+            (Some(cancellations), Some(uuid)) => cancellations.is_cancelled(uuid),
+            _ => false,
+        }
     }
 
     #[inline]
@@ -37,47 +62,158 @@ impl<'a> BillExtractor<'a> {
             return None;
         }
 
+        if !self.receipt_type_match(&bill) {
+            return None;
+        }
+
         Some(bill)
     }
 
-    pub fn extract_bills<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Bill>, String> {
-        Ok(self.extract_as_iter(path)?.collect())
+    #[inline]
+    fn filter_result(
+        &self,
+        result: Result<Bill, SkippedFile>,
+    ) -> Option<Result<Bill, SkippedFile>> {
+        match result {
+            Ok(bill) => self.filter(bill).map(Ok),
+            Err(skipped) => Some(Err(skipped)),
+        }
+    }
+
+    /// Extrae las facturas separando las descartadas por tener un UUID repetido, las
+    /// canceladas y los archivos que no pudieron leerse.
+    pub fn extract<P: AsRef<Path>>(&self, path: P) -> Result<Extraction, SatrError> {
+        let mut deduplicator = Deduplicator::new(self.config.deduplicate);
+        let mut extraction = Extraction::default();
+        let mut push = |result: Result<Bill, SkippedFile>| match result {
+            Ok(bill) if !deduplicator.is_unique(bill.uuid()) => extraction.duplicates.push(bill),
+            Ok(bill) if self.is_cancelled(&bill) => extraction.cancelled.push(bill),
+            Ok(bill) => extraction.bills.push(bill),
+            Err(skipped) => extraction.skipped.push(skipped),
+        };
+
+        self.extract_as_iter(path)?.for_each(&mut push);
+
+        Ok(extraction)
     }
 
+    /// Itera sobre las facturas que cumplen con los filtros, sin descartar UUID repetidos.
+    /// Los archivos se leen por lotes conforme se solicitan las facturas y se deserializan
+    /// en paralelo, conservando el orden en que se encontraron. Si la carpeta tiene un
+    /// índice, se consulta y actualiza en lugar de leer todos los archivos.
+    /// Las constancias de retenciones se ignoran y los archivos que no pueden leerse, o no
+    /// como CFDI, se devuelven como [`SkippedFile`]. Solo falla si no puede leerse la
+    /// carpeta.
     pub fn extract_as_iter<P: AsRef<Path>>(
         &self,
         path: P,
-    ) -> Result<impl Iterator<Item = Bill> + '_, String> {
-        let files_content = XmlExtractor::load_all_xmls(path)?;
+    ) -> Result<impl Iterator<Item = Result<Bill, SkippedFile>> + '_, SatrError> {
+        let parse = |source: Result<XmlSource, SkippedFile>| match source {
+            Ok(s) if s.root().kind == DocumentKind::Retention => None,
+            source => Some(source.and_then(Bill::from_source)),
+        };
+        let path = path.as_ref();
+        let jobs = parallel::jobs(self.config.jobs);
+        let results: Box<dyn Iterator<Item = Result<Bill, SkippedFile>>> = if Index::exists(path) {
+            Box::new(Index::sync(path, jobs)?.into_iter())
+        } else {
+            Box::new(OrderedMap::new(XmlExtractor::iter_xmls(path)?, jobs, parse).flatten())
+        };
+
+        Ok(results.filter_map(|result| self.filter_result(result)))
+    }
+}
+
+/// Resultado de una extracción, con las facturas repetidas o canceladas que fueron
+/// descartadas y los archivos omitidos.
+#[derive(Debug, Default)]
+pub struct Extraction {
+    pub bills: Vec<Bill>,
+    pub duplicates: Vec<Bill>,
+    pub cancelled: Vec<Bill>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Archivo xml que no pudo leerse como CFDI.
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub origin: Origin,
+    pub error: SatrError,
+}
+
+/// Descarta los documentos cuyo UUID ya fue visto.
+pub struct Deduplicator {
+    enabled: bool,
+    seen: HashSet<String>,
+}
 
-        let iter = files_content
-            .into_iter()
-            .map(|file| quick_xml::de::from_str(file.as_str()))
-            .filter_map(Result::ok)
-            .filter_map(|b| self.filter(b));
+impl Deduplicator {
+    pub fn new(enabled: bool) -> Self {
+        Deduplicator {
+            enabled,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Los documentos sin timbre fiscal nunca se consideran repetidos.
+    pub fn is_unique(&mut self, uuid: Option<&str>) -> bool {
+        if !self.enabled {
+            return true;
+        }
 
-        Ok(iter)
+        match uuid {
+            Some(uuid) => self.seen.insert(uuid.to_uppercase()),
+            None => true,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub subject_rfc: String,
+    /// RFC del emisor o receptor, si no se indica se incluyen las facturas de todos.
+    pub subject_rfc: Option<String>,
     pub subject_type: SubjectType,
     pub dates: DateConfig,
+    pub deduplicate: bool,
+
+    /// Tipos de comprobante a incluir, si está vacío se incluyen todos.
+    pub receipt_types: Vec<ReceiptType>,
+
+    /// Facturas canceladas que se descartan.
+    pub cancellations: Option<Arc<CancellationList>>,
+
+    /// Hilos para deserializar las facturas, cero para usar uno por núcleo.
+    pub jobs: usize,
 }
 
 impl Config {
-    pub fn init<S: Into<SubjectType>>(
-        rfc: String,
-        subject: S,
-        date_start: Option<NaiveDate>,
-        date_end: Option<NaiveDate>,
-    ) -> Self {
+    /// Todas las facturas del RFC, sin filtrar por fechas ni tipos y descartando los UUID
+    /// repetidos. Los demás campos pueden cambiarse con la sintaxis de actualización.
+    pub fn new<S: Into<SubjectType>>(rfc: impl Into<String>, subject: S) -> Self {
         Config {
-            subject_rfc: rfc,
+            subject_rfc: Some(rfc.into()),
             subject_type: subject.into(),
-            dates: DateConfig::from((date_start, date_end)),
+            ..Config::default()
+        }
+    }
+
+    #[inline]
+    pub fn receipt_type_match(&self, bill: &Bill) -> bool {
+        self.receipt_types.is_empty() || self.receipt_types.contains(&bill.receipt_type)
+    }
+}
+
+/// Todas las facturas de cualquier RFC y fecha.
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            subject_rfc: None,
+            subject_type: SubjectType::Emisor,
+            dates: DateConfig::default(),
+            deduplicate: true,
+            receipt_types: Vec::new(),
+            cancellations: None,
+            jobs: 0,
         }
     }
 }
@@ -88,6 +224,23 @@ pub struct DateConfig {
     pub date_end: NaiveDateTime,
 }
 
+impl DateConfig {
+    #[inline]
+    pub fn contains(&self, date: NaiveDateTime) -> bool {
+        date >= self.date_start && date <= self.date_end
+    }
+}
+
+/// Sin límite de fechas, incluye también las facturas con fecha futura.
+impl Default for DateConfig {
+    fn default() -> Self {
+        DateConfig {
+            date_start: NaiveDateTime::MIN,
+            date_end: NaiveDateTime::MAX,
+        }
+    }
+}
+
 impl From<(Option<NaiveDate>, Option<NaiveDate>)> for DateConfig {
     fn from((start, end): (Option<NaiveDate>, Option<NaiveDate>)) -> Self {
         let date_start = start.unwrap_or_else(|| NaiveDate::from_ymd_opt(1900, 1, 1).unwrap());
@@ -0,0 +1,114 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use crate::{
+    error::SatrError,
+    metadata::{self, MetadataRecord},
+};
+
+/// UUID de las facturas canceladas, leídos de un archivo local.
+///
+/// Se aceptan archivos de metadatos de la descarga masiva del SAT, ver [`Metadata`], y
+/// archivos CSV o TXT separados por coma, punto y coma, tabulador o `|`. Sin encabezado
+/// cada línea tiene un UUID, opcionalmente seguido de su estatus (`cancelado`, `vigente`,
+/// `0` o `1`). Con más columnas se requiere un encabezado con la columna `UUID` y,
+/// opcionalmente, `Estatus`, `Status` o `Estado`; las demás se ignoran. Los UUID sin
+/// estatus se consideran cancelados.
+///
+/// [`Metadata`]: crate::metadata::Metadata
+#[derive(Debug, Clone, Default)]
+pub struct CancellationList {
+    cancelled: HashSet<String>,
+}
+
+impl CancellationList {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, SatrError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+
+        Self::parse(&contents).map_err(|line| SatrError::CancellationList {
+            path: path.to_path_buf(),
+            line,
+        })
+    }
+
+    /// En caso de error devuelve el número de la línea inválida.
+    pub fn parse(contents: &str) -> Result<Self, usize> {
+        let mut cancelled = HashSet::new();
+        let mut columns: Option<Columns> = None;
+
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Los nombres en los metadatos del SAT pueden contener comas.
+            if line.contains('~') {
+                if MetadataRecord::is_header(line) {
+                    continue;
+                }
+
+                let record = MetadataRecord::from_line(line).ok_or(n + 1)?;
+                if record.is_cancelled() {
+                    cancelled.insert(record.uuid);
+                }
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split([',', ';', '\t', '|']).map(str::trim).collect();
+            if let Some(header) = Columns::from_header(&fields) {
+                columns = Some(header);
+                continue;
+            }
+
+            let (uuid, status) = match &columns {
+                Some(columns) => (columns.uuid, columns.status),
+                None if fields.len() <= 2 => (0, Some(1)),
+                None => return Err(n + 1),
+            };
+            let uuid = fields.get(uuid).copied().ok_or(n + 1)?;
+            let status = status.and_then(|i| fields.get(i)).copied().unwrap_or("0");
+
+            if !metadata::is_uuid(uuid) {
+                return Err(n + 1);
+            }
+
+            match status.to_lowercase().as_str() {
+                "0" | "cancelado" | "cancelada" => {
+                    cancelled.insert(uuid.to_uppercase());
+                }
+                "1" | "vigente" => {}
+                _ => return Err(n + 1),
+            }
+        }
+
+        Ok(CancellationList { cancelled })
+    }
+
+    pub fn is_cancelled(&self, uuid: &str) -> bool {
+        self.cancelled.contains(&uuid.to_uppercase())
+    }
+}
+
+/// Posición de las columnas según el encabezado de un CSV.
+struct Columns {
+    uuid: usize,
+    status: Option<usize>,
+}
+
+impl Columns {
+    const STATUS_NAMES: [&'static str; 3] = ["estatus", "status", "estado"];
+
+    /// Solo si la línea tiene una columna llamada `UUID`.
+    fn from_header(fields: &[&str]) -> Option<Self> {
+        let uuid = fields.iter().position(|f| f.eq_ignore_ascii_case("uuid"))?;
+        let status = fields.iter().position(|f| {
+            Self::STATUS_NAMES
+                .iter()
+                .any(|name| f.eq_ignore_ascii_case(name))
+        });
+
+        Some(Columns { uuid, status })
+    }
+}
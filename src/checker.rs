@@ -0,0 +1,89 @@
+use std::{borrow::Cow, path::Path};
+
+use crate::{
+    bill_extractor::{self, BillExtractor, Deduplicator, SkippedFile},
+    error::SatrError,
+    xml_extractor::Origin,
+};
+
+/// Revisa los archivos de una carpeta con el mismo proceso de extracción que los reportes:
+/// se consulta el índice si existe y las constancias de retenciones no se incluyen, se
+/// revisan con `satr retenciones`.
+pub struct Checker;
+
+impl Checker {
+    pub fn check<P: AsRef<Path>>(path: P, jobs: usize) -> Result<Vec<FileCheck>, SatrError> {
+        let config = bill_extractor::Config {
+            jobs,
+            ..Default::default()
+        };
+        let extractor = BillExtractor::new(Cow::Owned(config));
+        let mut deduplicator = Deduplicator::new(true);
+
+        let checks = extractor
+            .extract_as_iter(path)?
+            .map(|result| match result {
+                Ok(mut bill) => {
+                    let uuid = bill.uuid().map(str::to_string);
+                    let status = if deduplicator.is_unique(uuid.as_deref()) {
+                        CheckStatus::Valid
+                    } else {
+                        CheckStatus::Duplicate
+                    };
+
+                    FileCheck {
+                        origin: bill.source.take(),
+                        version: Some(bill.version.to_string()),
+                        uuid,
+                        status,
+                    }
+                }
+                Err(SkippedFile {
+                    origin,
+                    error: SatrError::NotCfdi,
+                }) => FileCheck {
+                    origin: Some(origin),
+                    version: None,
+                    uuid: None,
+                    status: CheckStatus::NotCfdi,
+                },
+                Err(SkippedFile { origin, error }) => FileCheck {
+                    origin: Some(origin),
+                    version: None,
+                    uuid: None,
+                    status: CheckStatus::Rejected(error),
+                },
+            })
+            .collect();
+
+        Ok(checks)
+    }
+}
+
+/// Diagnóstico de un archivo xml.
+#[derive(Debug)]
+pub struct FileCheck {
+    /// Archivo o entrada de donde se leyó.
+    pub origin: Option<Origin>,
+
+    /// Versión del CFDI, solo si pudo leerse.
+    pub version: Option<String>,
+
+    pub uuid: Option<String>,
+    pub status: CheckStatus,
+}
+
+#[derive(Debug)]
+pub enum CheckStatus {
+    /// La factura se incluye en los reportes.
+    Valid,
+
+    /// Otro archivo contiene la misma factura, se descarta en los reportes.
+    Duplicate,
+
+    /// El archivo no pudo abrirse, o es un CFDI que no pudo leerse.
+    Rejected(SatrError),
+
+    /// El elemento raíz no es `cfdi:Comprobante`.
+    NotCfdi,
+}
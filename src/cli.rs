@@ -1,10 +1,25 @@
+use std::process;
+
 use clap::Parser;
 
+use crate::{bill::Bill, bill_extractor::SkippedFile, error::SatrError, xml_extractor::Origin};
+use check::CheckSubCommand;
+use download::DownloadSubCommand;
+use index::IndexSubCommand;
 use list::ListSubCommand;
+use reconcile::ReconcileSubCommand;
+use related::RelatedSubCommand;
 use report::ReportSubCommand;
+use retentions::RetentionsSubCommand;
 
+pub mod check;
+pub mod download;
+pub mod index;
 pub mod list;
+pub mod reconcile;
+pub mod related;
 pub mod report;
+pub mod retentions;
 
 #[derive(Debug, Parser)]
 #[command(version)]
@@ -27,6 +42,27 @@ pub enum Action {
     /// Lista una breve descripción de las facturas según los parámetros seleccionados.
     Ls(ListSubCommand),
 
+    /// Imprime el ISR y el IVA retenidos a un RFC según sus constancias de retenciones, por
+    /// retenedor.
+    Retenciones(RetentionsSubCommand),
+
+    /// Muestra los comprobantes relacionados con una factura y los que la relacionan.
+    Related(RelatedSubCommand),
+
+    /// Revisa que los archivos de una carpeta puedan leerse como facturas.
+    Check(CheckSubCommand),
+
+    /// Crea o revisa el índice de facturas de una carpeta.
+    Index(IndexSubCommand),
+
+    /// Compara los metadatos de la descarga masiva del SAT con las facturas de una carpeta:
+    /// facturas sin XML, canceladas de las que se tiene el XML y montos distintos.
+    Reconcile(ReconcileSubCommand),
+
+    /// Descarga las facturas o sus metadatos con el servicio de descarga masiva del SAT,
+    /// usando la e.firma. Los paquetes se guardan como zip en la carpeta indicada.
+    Download(DownloadSubCommand),
+
     /// Imprime el contenido de todas las facturas.
     Print,
 
@@ -39,7 +75,53 @@ impl Action {
         match self {
             Action::Report(cmd) => cmd.run(),
             Action::Ls(cmd) => cmd.run(),
+            Action::Check(cmd) => cmd.run(),
+            Action::Index(cmd) => cmd.run(),
+            Action::Reconcile(cmd) => cmd.run(),
+            Action::Download(cmd) => cmd.run(),
+            Action::Related(cmd) => cmd.run(),
+            Action::Retenciones(cmd) => cmd.run(),
             _ => println!("Pronto disponible"),
         }
     }
 }
+
+/// Imprime en stderr las facturas descartadas por tener un UUID repetido.
+pub fn print_duplicates(duplicates: &[Bill]) {
+    eprintln!("Facturas duplicadas descartadas: {}", duplicates.len());
+    for bill in duplicates {
+        eprintln!(
+            "  {} | {} | {} | {}",
+            bill.uuid().unwrap_or_default(),
+            bill.date,
+            bill.emisor.rfc,
+            bill.source
+                .as_ref()
+                .map(Origin::to_string)
+                .unwrap_or_default()
+        );
+    }
+}
+
+/// Imprime en stderr los archivos que no pudieron leerse como CFDI. En modo estricto
+/// termina el programa con error si hubo alguno.
+pub fn print_skipped(skipped: &[SkippedFile], strict: bool) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    eprintln!("Archivos omitidos: {}", skipped.len());
+    for file in skipped {
+        eprintln!("  {} | {}", file.origin, file.error);
+    }
+
+    if strict {
+        process::exit(1);
+    }
+}
+
+/// Imprime el error en stderr y termina el programa.
+pub fn exit_with_error(e: SatrError) -> ! {
+    eprintln!("{e}");
+    process::exit(1);
+}
@@ -0,0 +1,91 @@
+use std::{env, path::PathBuf};
+
+use clap::Parser;
+
+use crate::{
+    checker::{CheckStatus, Checker, FileCheck},
+    cli::exit_with_error,
+};
+
+#[derive(Debug, Parser)]
+pub struct CheckSubCommand {
+    /// Imprime solo los archivos duplicados, rechazados o que no son CFDI.
+    #[clap(long)]
+    pub problems: bool,
+
+    /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+    #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+
+    #[clap(value_name = "PATH")]
+    /// Carpeta desde donde se extraerán recursivamente las facturas.
+    pub path: Option<PathBuf>,
+}
+
+impl CheckSubCommand {
+    #[inline]
+    fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual"))
+    }
+
+    pub fn run(&self) {
+        let checks = Checker::check(self.path(), self.jobs).unwrap_or_else(|e| exit_with_error(e));
+        let mut summary = Summary::default();
+
+        for check in &checks {
+            match check.status {
+                CheckStatus::Valid => summary.valid += 1,
+                CheckStatus::Duplicate => summary.duplicates += 1,
+                CheckStatus::Rejected(_) => summary.rejected += 1,
+                CheckStatus::NotCfdi => summary.not_cfdi += 1,
+            }
+
+            if self.problems && matches!(check.status, CheckStatus::Valid) {
+                continue;
+            }
+
+            Self::print_check(check);
+        }
+
+        println!();
+        println!(
+            "{} archivos: {} válidos, {} duplicados, {} rechazados, {} no son CFDI",
+            checks.len(),
+            summary.valid,
+            summary.duplicates,
+            summary.rejected,
+            summary.not_cfdi
+        );
+    }
+
+    fn print_check(check: &FileCheck) {
+        let status = match &check.status {
+            CheckStatus::Valid => "OK".to_string(),
+            CheckStatus::Duplicate => "DUPLICADO".to_string(),
+            CheckStatus::Rejected(e) => format!("RECHAZADO: {e}"),
+            CheckStatus::NotCfdi => "NO ES CFDI".to_string(),
+        };
+
+        println!(
+            "{} | {:3} | {:36} | {}",
+            check
+                .origin
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |origin| origin.to_string()),
+            check.version.as_deref().unwrap_or("-"),
+            check.uuid.as_deref().unwrap_or("-"),
+            status
+        );
+    }
+}
+
+/// Archivos revisados por estatus.
+#[derive(Debug, Default)]
+struct Summary {
+    valid: usize,
+    duplicates: usize,
+    rejected: usize,
+    not_cfdi: usize,
+}
@@ -0,0 +1,171 @@
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal},
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+use clap::{Args, Parser};
+
+use crate::{
+    cli::{exit_with_error, report::SubjectType},
+    downloader::{self, fiel::Fiel, Downloader, Endpoints, RequestType},
+    error::SatrError,
+};
+
+/// Variable de entorno con la contraseña de la llave privada.
+const PASSWORD_VAR: &str = "SATR_FIEL_PASSWORD";
+
+/// Tiempo entre cada verificación de la solicitud.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Parser)]
+pub struct DownloadSubCommand {
+    #[clap(subcommand)]
+    pub subject: DownloadSubject,
+}
+
+#[derive(Debug, Parser)]
+pub enum DownloadSubject {
+    /// Facturas emitidas por el RFC de la e.firma.
+    Emisor(DownloadArgs),
+
+    /// Facturas recibidas por el RFC de la e.firma; el SAT solo entrega los xml de las
+    /// vigentes.
+    Receptor(DownloadArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct DownloadArgs {
+    /// RFC de la e.firma.
+    #[clap(value_name = "RFC")]
+    pub rfc: String,
+
+    #[clap(long, short = 's')]
+    /// Fecha inicial de emisión de las facturas. Usar formato YYYY-MM-DD.
+    pub date_start: NaiveDate,
+
+    #[clap(long, short = 'e')]
+    /// Fecha final de emisión de las facturas, incluida. Usar formato YYYY-MM-DD.
+    pub date_end: NaiveDate,
+
+    /// Certificado de la e.firma (.cer).
+    #[clap(long, value_name = "FILE")]
+    pub cer: PathBuf,
+
+    /// Llave privada de la e.firma (.key). Su contraseña se lee de la variable
+    /// SATR_FIEL_PASSWORD o se solicita al ejecutar el comando.
+    #[clap(long, value_name = "FILE")]
+    pub key: PathBuf,
+
+    /// Descarga los metadatos en lugar de los xml, que incluyen las facturas canceladas.
+    /// Se extraen de cada paquete como `<IdPaquete>.txt`.
+    #[clap(long = "metadatos")]
+    pub metadata: bool,
+
+    /// Continúa una solicitud anterior en lugar de crear una nueva.
+    #[clap(long = "solicitud", value_name = "ID")]
+    pub request_id: Option<String>,
+
+    /// Minutos máximos de espera a que el SAT termine la solicitud.
+    #[clap(long = "espera", value_name = "MIN", default_value_t = 30)]
+    pub timeout: u64,
+
+    /// URL base de los servicios, por defecto los del SAT. Permite usar un servidor de
+    /// pruebas con las mismas rutas.
+    #[clap(long, value_name = "URL")]
+    pub endpoint: Option<String>,
+
+    #[clap(value_name = "PATH")]
+    /// Carpeta donde se guardarán los paquetes.
+    pub path: Option<PathBuf>,
+}
+
+impl DownloadSubCommand {
+    pub fn run(&self) {
+        let (subject, args) = match &self.subject {
+            DownloadSubject::Emisor(args) => (SubjectType::Emisor, args),
+            DownloadSubject::Receptor(args) => (SubjectType::Receptor, args),
+        };
+
+        let password = Self::password().unwrap_or_else(|e| exit_with_error(e));
+        let fiel = Fiel::from_files(&args.cer, &args.key, &password)
+            .unwrap_or_else(|e| exit_with_error(e));
+        let downloader = Downloader::new(args.config(subject), fiel);
+
+        let request_id = match &args.request_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = downloader.request().unwrap_or_else(|e| exit_with_error(e));
+                eprintln!("Solicitud aceptada: {id}");
+                id
+            }
+        };
+
+        eprintln!("Esperando a que el SAT termine la solicitud {request_id}...");
+        let download = downloader
+            .download(&request_id)
+            .unwrap_or_else(|e| exit_with_error(e));
+
+        eprintln!(
+            "Paquetes descargados: {} ({} comprobantes)",
+            download.packages.len(),
+            download.cfdis
+        );
+        for package in download.packages {
+            println!("{}", package.display());
+        }
+        for metadata in download.metadata {
+            println!("{}", metadata.display());
+        }
+    }
+
+    /// Contraseña de la variable de entorno, o de la terminal sin mostrarla. Si stdin no es
+    /// una terminal, por ejemplo en una tubería, se lee su primera línea.
+    fn password() -> Result<String, SatrError> {
+        if let Ok(password) = env::var(PASSWORD_VAR) {
+            return Ok(password);
+        }
+
+        let stdin = io::stdin();
+        if stdin.is_terminal() {
+            return rpassword::prompt_password("Contraseña de la llave privada: ")
+                .map_err(SatrError::Password);
+        }
+
+        let mut password = String::new();
+        stdin
+            .lock()
+            .read_line(&mut password)
+            .map_err(SatrError::Password)?;
+
+        Ok(password.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+impl DownloadArgs {
+    fn config(&self, subject: SubjectType) -> downloader::Config {
+        downloader::Config {
+            rfc: self.rfc.to_uppercase(),
+            subject,
+            start: self.date_start.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            end: self.date_end.and_hms_opt(23, 59, 59).unwrap_or_default(),
+            request_type: if self.metadata {
+                RequestType::Metadata
+            } else {
+                RequestType::Cfdi
+            },
+            endpoints: self
+                .endpoint
+                .as_deref()
+                .map_or_else(Endpoints::sat, Endpoints::with_base),
+            poll_interval: POLL_INTERVAL,
+            timeout: Duration::from_secs(self.timeout * 60),
+            output: self
+                .path
+                .clone()
+                .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual")),
+        }
+    }
+}
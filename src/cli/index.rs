@@ -0,0 +1,91 @@
+use std::{env, path::PathBuf};
+
+use clap::Parser;
+
+use crate::{
+    cli::{exit_with_error, print_skipped},
+    index::Index,
+    parallel,
+};
+
+#[derive(Debug, Parser)]
+pub struct IndexSubCommand {
+    #[clap(subcommand)]
+    pub action: IndexAction,
+}
+
+#[derive(Debug, Parser)]
+pub enum IndexAction {
+    /// Crea de nuevo el índice de la carpeta leyendo todos los archivos. Una vez creado,
+    /// `report`, `ls`, `check` y `related` lo consultan y actualizan con los archivos
+    /// nuevos o modificados.
+    Rebuild {
+        /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+        #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+        jobs: usize,
+
+        #[clap(value_name = "PATH")]
+        /// Carpeta a indexar.
+        path: Option<PathBuf>,
+    },
+
+    /// Compara el índice con los archivos de la carpeta sin leerlos.
+    Status {
+        #[clap(value_name = "PATH")]
+        /// Carpeta indexada.
+        path: Option<PathBuf>,
+    },
+}
+
+impl IndexSubCommand {
+    pub fn run(&self) {
+        match &self.action {
+            IndexAction::Rebuild { jobs, path } => Self::rebuild(Self::path(path), *jobs),
+            IndexAction::Status { path } => Self::status(Self::path(path)),
+        }
+    }
+
+    #[inline]
+    fn path(path: &Option<PathBuf>) -> PathBuf {
+        path.clone()
+            .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual"))
+    }
+
+    fn rebuild(path: PathBuf, jobs: usize) {
+        let (bills, skipped): (Vec<_>, Vec<_>) = Index::rebuild(&path, parallel::jobs(jobs))
+            .unwrap_or_else(|e| exit_with_error(e))
+            .into_iter()
+            .partition(Result::is_ok);
+        let skipped: Vec<_> = skipped.into_iter().filter_map(Result::err).collect();
+
+        print_skipped(&skipped, false);
+        println!(
+            "Índice creado en {}: {} facturas",
+            Index::path(&path).display(),
+            bills.len()
+        );
+    }
+
+    fn status(path: PathBuf) {
+        if !Index::exists(&path) {
+            println!(
+                "No existe un índice en {}, puede crearse con `satr index rebuild`",
+                path.display()
+            );
+            return;
+        }
+
+        let status = Index::status(&path).unwrap_or_else(|e| exit_with_error(e));
+        println!("Índice: {}", Index::path(&path).display());
+        println!("Archivos indexados: {}", status.files);
+        println!("Facturas: {}", status.bills);
+        println!("Entradas con errores: {}", status.skipped);
+        println!("Archivos sin indexar: {}", status.unindexed);
+        println!("Archivos modificados: {}", status.modified);
+        println!("Archivos eliminados: {}", status.removed);
+
+        if status.is_up_to_date() {
+            println!("El índice está actualizado");
+        }
+    }
+}
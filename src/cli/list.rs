@@ -5,7 +5,9 @@ use clap::{Parser, ValueEnum};
 use Lotus::Lotus;
 
 use crate::{
-    bill_extractor::{self, BillExtractor},
+    bill::{Bill, ReceiptType},
+    bill_extractor::{self, BillExtractor, DateConfig, Extraction},
+    cli::{exit_with_error, print_duplicates, print_skipped},
     parse_dec,
 };
 
@@ -28,6 +30,26 @@ pub struct ListSubCommand {
     #[clap(long, short = 'e')]
     pub date_end: Option<NaiveDate>,
 
+    /// Solo se incluirán los comprobantes de este tipo, puede indicarse varias veces.
+    #[clap(long = "tipo", value_enum)]
+    pub receipt_types: Vec<ReceiptType>,
+
+    /// Archivo con los UUID de las facturas canceladas: metadatos de la descarga masiva del
+    /// SAT, o CSV/TXT con un UUID por línea y opcionalmente su estatus.
+    #[clap(long = "canceladas", value_name = "FILE")]
+    pub cancellations: Option<PathBuf>,
+
+    /// Termina con error si algún archivo no pudo leerse como CFDI.
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+    #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+
+    #[command(flatten)]
+    pub dedup: report::DedupArgs,
+
     #[clap(value_name = "PATH")]
     /// Carpeta desde donde se extraerán recursivamente las facturas.
     pub path: Option<PathBuf>,
@@ -35,13 +57,16 @@ pub struct ListSubCommand {
 
 impl ListSubCommand {
     #[inline]
-    fn extractor(&self) -> BillExtractor {
-        let config = bill_extractor::Config::init(
-            self.rfc.clone(),
-            self.subject,
-            self.date_start,
-            self.date_end,
-        );
+    fn extractor(&self) -> BillExtractor<'_> {
+        let config = bill_extractor::Config {
+            dates: DateConfig::from((self.date_start, self.date_end)),
+            deduplicate: self.dedup.deduplicate(),
+            receipt_types: self.receipt_types.clone(),
+            cancellations: report::load_cancellations(self.cancellations.as_ref())
+                .unwrap_or_else(|e| exit_with_error(e)),
+            jobs: self.jobs,
+            ..bill_extractor::Config::new(self.rfc.clone(), self.subject)
+        };
 
         BillExtractor::new(Cow::Owned(config))
     }
@@ -55,25 +80,76 @@ impl ListSubCommand {
 
     pub fn run(&self) {
         let extractor = self.extractor();
-        let mut bills = extractor
-            .extract_bills(self.path())
-            .expect("Error al obtener facturas");
-        bills.sort_by(|a, b| a.date.cmp(&b.date));
+        let Extraction {
+            bills,
+            duplicates,
+            cancelled,
+            skipped,
+        } = extractor
+            .extract(self.path())
+            .unwrap_or_else(|e| exit_with_error(e));
+        let cancelled_count = cancelled.len();
+        let mut bills: Vec<(Bill, bool)> = bills
+            .into_iter()
+            .map(|b| (b, false))
+            .chain(cancelled.into_iter().map(|b| (b, true)))
+            .collect();
+        bills.sort_by_key(|(b, _)| b.date);
+
+        print_skipped(&skipped, self.strict);
+
+        if self.dedup.duplicates {
+            print_duplicates(&duplicates);
+        }
 
         let formatter = Lotus::new("$", 2);
 
-        println!("----------------------------------------------------------------");
-        println!("Fecha               | Emisor        | Receptor     | Total");
-        println!("----------------------------------------------------------------");
-        for bill in bills {
+        println!("------------------------------------------------------------------------------------------------------------");
+        println!("Fecha               | Tipo | UUID                                 | Emisor        | Receptor     | Total");
+        println!("------------------------------------------------------------------------------------------------------------");
+        for (bill, is_cancelled) in bills {
             println!(
-                "{} | {} | {} | {}",
+                "{} | {:4} | {:36} | {} | {} | {} {}",
                 bill.date,
+                bill.receipt_type.to_string(),
+                bill.uuid().unwrap_or("Sin timbrar"),
                 bill.emisor.rfc,
                 bill.recipient.rfc,
                 formatter.format(parse_dec!(bill.total())),
+                bill.currency,
             );
 
+            if is_cancelled {
+                println!("  CANCELADA");
+            }
+
+            if let Some(origin) = &bill.source {
+                println!("  Archivo: {origin}");
+            }
+
+            if let Some(local) = &bill.complement.local_taxes {
+                for tax in &local.carried_forward {
+                    let value = formatter.format(parse_dec!(tax.value));
+                    println!(
+                        "  Impuesto local trasladado {} {}%: {value}",
+                        tax.name, tax.rate
+                    );
+                }
+
+                for tax in &local.withheld {
+                    let value = formatter.format(parse_dec!(tax.value));
+                    println!(
+                        "  Impuesto local retenido {} {}%: {value}",
+                        tax.name, tax.rate
+                    );
+                }
+            }
+
+            if !bill.reconciles() {
+                let computed = formatter.format(parse_dec!(bill.computed_total()));
+                println!("  ¡El total no coincide con el calculado: {computed}!");
+            }
+
             for (n, concept) in bill.concepts.list.iter().enumerate() {
                 let value = formatter.format(parse_dec!(concept.value));
                 println!("  {}.- {} - {}", n + 1, concept.description, value)
@@ -81,6 +157,10 @@ impl ListSubCommand {
 
             println!();
         }
+
+        if cancelled_count > 0 {
+            println!("Facturas canceladas: {cancelled_count}");
+        }
     }
 }
 
@@ -0,0 +1,109 @@
+use std::{env, path::PathBuf};
+
+use clap::Parser;
+use rust_decimal::Decimal;
+use Lotus::Lotus;
+
+use crate::{
+    bill::Bill,
+    cli::{exit_with_error, print_skipped},
+    metadata::{Metadata, MetadataRecord},
+    parse_dec,
+    reconciler::Reconciler,
+    xml_extractor::Origin,
+};
+
+#[derive(Debug, Parser)]
+pub struct ReconcileSubCommand {
+    /// Archivo de metadatos de la descarga masiva del SAT.
+    #[clap(value_name = "METADATA")]
+    pub metadata: PathBuf,
+
+    /// Termina con error si algún archivo no pudo leerse como CFDI.
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+    #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+
+    #[clap(value_name = "PATH")]
+    /// Carpeta desde donde se extraerán recursivamente las facturas.
+    pub path: Option<PathBuf>,
+}
+
+impl ReconcileSubCommand {
+    #[inline]
+    fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual"))
+    }
+
+    pub fn run(&self) {
+        let metadata = Metadata::from_path(&self.metadata).unwrap_or_else(|e| exit_with_error(e));
+        let reconciliation = Reconciler::reconcile(&metadata, self.path(), self.jobs)
+            .unwrap_or_else(|e| exit_with_error(e));
+
+        print_skipped(&reconciliation.skipped, self.strict);
+
+        let formatter = Lotus::new("$", 2);
+        let format = |n: Decimal| formatter.format(parse_dec!(n));
+        let describe = |record: &MetadataRecord| {
+            format!(
+                "{} | {} | {} | {} | {} | {}",
+                record.uuid,
+                record.date,
+                record.receipt_type,
+                record.issuer_rfc,
+                record.recipient_rfc,
+                format(record.amount)
+            )
+        };
+        let origin = |bill: &Bill| {
+            bill.source
+                .as_ref()
+                .map(Origin::to_string)
+                .unwrap_or_default()
+        };
+
+        println!("XML faltantes: {}", reconciliation.missing.len());
+        for record in &reconciliation.missing {
+            println!("  {} | {}", describe(record), record.issuer_name);
+        }
+
+        println!("Canceladas con XML: {}", reconciliation.cancelled.len());
+        for (record, bill) in &reconciliation.cancelled {
+            println!(
+                "  {} | Cancelada {} | {}",
+                describe(record),
+                record
+                    .cancellation_date
+                    .map(|d| d.to_string())
+                    .unwrap_or_default(),
+                origin(bill)
+            );
+        }
+
+        println!("Montos distintos: {}", reconciliation.mismatched.len());
+        for (record, bill) in &reconciliation.mismatched {
+            println!(
+                "  {} | XML {} | Diferencia {} | {}",
+                describe(record),
+                format(bill.total()),
+                format(bill.total() - record.amount),
+                origin(bill)
+            );
+        }
+
+        println!();
+        println!(
+            "{} facturas en los metadatos: {} coinciden, {} sin XML, {} canceladas con XML, {} con monto distinto",
+            metadata.records.len(),
+            reconciliation.matched,
+            reconciliation.missing.len(),
+            reconciliation.cancelled.len(),
+            reconciliation.mismatched.len()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::{borrow::Cow, env, path::PathBuf};
+
+use clap::Parser;
+use Lotus::Lotus;
+
+use crate::{
+    bill::Bill,
+    bill_extractor::{self, BillExtractor},
+    cli::{exit_with_error, print_skipped},
+    parse_dec,
+    relations::RelationGraph,
+};
+
+#[derive(Debug, Parser)]
+pub struct RelatedSubCommand {
+    /// UUID de la factura.
+    #[clap(value_name = "UUID")]
+    pub uuid: String,
+
+    /// Termina con error si algún archivo no pudo leerse como CFDI.
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+    #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+
+    #[clap(value_name = "PATH")]
+    /// Carpeta desde donde se extraerán recursivamente las facturas.
+    pub path: Option<PathBuf>,
+}
+
+impl RelatedSubCommand {
+    #[inline]
+    fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual"))
+    }
+
+    pub fn run(&self) {
+        let config = bill_extractor::Config {
+            jobs: self.jobs,
+            ..Default::default()
+        };
+        let extraction = BillExtractor::new(Cow::Owned(config))
+            .extract(self.path())
+            .unwrap_or_else(|e| exit_with_error(e));
+        print_skipped(&extraction.skipped, self.strict);
+
+        let bills = extraction.bills;
+        let graph = RelationGraph::new(&bills);
+        let formatter = Lotus::new("$", 2);
+        let describe = |bill: &Bill| {
+            format!(
+                "{} | {} | {} | {} | {}",
+                bill.date,
+                bill.receipt_type,
+                bill.emisor.rfc,
+                bill.recipient.rfc,
+                formatter.format(parse_dec!(bill.total())),
+            )
+        };
+
+        match graph.get(&self.uuid) {
+            Some(bill) => {
+                println!("{} | {}", self.uuid.to_uppercase(), describe(bill));
+
+                println!("Relaciona a:");
+                for (relation_type, uuid, related) in graph.outgoing(bill) {
+                    let related = related.map(describe);
+                    println!(
+                        "  {} {} | {} | {}",
+                        relation_type,
+                        relation_type.description(),
+                        uuid,
+                        related.as_deref().unwrap_or("No encontrada")
+                    );
+                }
+            }
+            None => println!("{} | No encontrada", self.uuid.to_uppercase()),
+        }
+
+        println!("Relacionada por:");
+        for (relation_type, bill) in graph.incoming(&self.uuid) {
+            println!(
+                "  {} {} | {} | {}",
+                relation_type,
+                relation_type.description(),
+                bill.uuid().unwrap_or_default(),
+                describe(bill)
+            );
+        }
+    }
+}
@@ -1,11 +1,19 @@
-use std::{env, path::PathBuf};
+use std::{env, path::PathBuf, sync::Arc};
 
 use chrono::NaiveDate;
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
 use enum_kinds::EnumKind;
+use rust_decimal::Decimal;
 use Lotus::Lotus;
 
-use crate::reporter_factory::ReporterFactory;
+use crate::{
+    bill::ReceiptType,
+    cancellations::CancellationList,
+    cli::{exit_with_error, print_duplicates, print_skipped},
+    error::SatrError,
+    reporter::{ConceptTotal, PayrollTotal},
+    reporter_factory::ReporterFactory,
+};
 
 #[derive(Debug, Parser)]
 pub struct ReportSubCommand {
@@ -15,11 +23,55 @@ pub struct ReportSubCommand {
 
 impl ReportSubCommand {
     pub fn run(&self) {
-        let reporter = ReporterFactory::from_subject(self.subject.clone());
+        let reporter = ReporterFactory::from_subject(self.subject.clone())
+            .unwrap_or_else(|e| exit_with_error(e));
         let args = &self.subject.sub_command().args();
-        let result = reporter
+        let report = reporter
             .money_report(args.path())
-            .expect("Error al realizar cálculo");
+            .unwrap_or_else(|e| exit_with_error(e));
+        let result = report.result;
+
+        print_skipped(&report.skipped, args.strict);
+
+        if !report.cancelled.is_empty() {
+            eprintln!("Facturas canceladas excluidas: {}", report.cancelled.len());
+        }
+
+        if args.dedup.duplicates {
+            print_duplicates(&report.duplicates);
+        }
+
+        if !report.unmatched_payments.is_empty() {
+            eprintln!("Pagos a facturas no encontradas:");
+            for uuid in &report.unmatched_payments {
+                eprintln!("  {uuid}");
+            }
+        }
+
+        if args.by_currency {
+            for total in &report.by_currency {
+                let line = if args.formatted() {
+                    format!(
+                        "{} {} = {}",
+                        total.currency,
+                        Self::format(total.amount),
+                        Self::format(total.mxn)
+                    )
+                } else {
+                    format!("{} {} {}", total.currency, total.amount, total.mxn)
+                };
+
+                println!("{line}");
+            }
+        }
+
+        if args.by == Some(Breakdown::Concepto) {
+            Self::print_concepts(&report.by_concept, args.formatted());
+        }
+
+        if matches!(self.subject.sub_command(), ReportTypeSubCommand::Nomina(_)) {
+            Self::print_payroll(&report.by_payroll, args.formatted());
+        }
 
         if !args.formatted() {
             println!("{result}");
@@ -27,11 +79,84 @@ impl ReportSubCommand {
             return;
         }
 
+        println!("{}", Self::format(result));
+    }
+
+    fn print_concepts(concepts: &[ConceptTotal], formatted: bool) {
+        if !formatted {
+            for c in concepts {
+                println!(
+                    "{} {} {} {} {}",
+                    c.key, c.quantity, c.amount, c.carried_forward, c.withheld
+                );
+            }
+
+            return;
+        }
+
+        println!("----------------------------------------------------------------------------------------------");
+        println!("Clave    | Cantidad     | Importe          | Trasladados      | Retenidos        | Descripción");
+        println!("----------------------------------------------------------------------------------------------");
+        for c in concepts {
+            println!(
+                "{:8} | {:>12} | {:>16} | {:>16} | {:>16} | {}",
+                c.key,
+                c.quantity.normalize().to_string(),
+                Self::format(c.amount),
+                Self::format(c.carried_forward),
+                Self::format(c.withheld),
+                c.description,
+            );
+        }
+        println!();
+    }
+
+    fn print_payroll(payroll: &[PayrollTotal], formatted: bool) {
+        if !formatted {
+            for p in payroll {
+                println!(
+                    "{} {} {} {} {} {} {} {} {}",
+                    p.employer_rfc,
+                    p.period,
+                    p.receipts,
+                    p.paid_days,
+                    p.gross,
+                    p.taxed,
+                    p.exempt,
+                    p.isr_withheld,
+                    p.employment_subsidy
+                );
+            }
+
+            return;
+        }
+
+        println!("-------------------------------------------------------------------------------------------------------------------------------------------");
+        println!("Patrón        | Mes     | Recibos | Días   | Percepciones     | Gravado          | Exento           | ISR retenido     | Subsidio         | Nombre");
+        println!("-------------------------------------------------------------------------------------------------------------------------------------------");
+        for p in payroll {
+            println!(
+                "{:13} | {:7} | {:>7} | {:>6} | {:>16} | {:>16} | {:>16} | {:>16} | {:>16} | {}",
+                p.employer_rfc,
+                p.period,
+                p.receipts,
+                p.paid_days.normalize().to_string(),
+                Self::format(p.gross),
+                Self::format(p.taxed),
+                Self::format(p.exempt),
+                Self::format(p.isr_withheld),
+                Self::format(p.employment_subsidy),
+                p.employer_name.as_deref().unwrap_or_default(),
+            );
+        }
+        println!();
+    }
+
+    fn format(n: Decimal) -> String {
         let formatter = Lotus::new("$", 2);
-        let n = f64::try_from(result).expect("Error al formatear resultado");
-        let formatted_result = formatter.format(n);
+        let n = f64::try_from(n).expect("Error al formatear resultado");
 
-        println!("{formatted_result}");
+        formatter.format(n)
     }
 }
 
@@ -67,10 +192,40 @@ impl Subject {
 #[derive(Debug, Parser, Clone, EnumKind)]
 #[enum_kind(ReportType)]
 pub enum ReportTypeSubCommand {
+    /// Total de las facturas, incluyendo impuestos.
     Total(ReportArgs),
+    /// Subtotal de las facturas menos descuentos, antes de impuestos.
     Subtotal(ReportArgs),
-    Iva(ReportArgs),
-    Isr(ReportArgs),
+    /// Descuentos de las facturas.
+    Descuento(ReportArgs),
+    /// IVA trasladado.
+    IvaTrasladado(ReportArgs),
+    /// IVA retenido.
+    IvaRetenido(ReportArgs),
+    /// IVA trasladado menos IVA retenido.
+    #[command(visible_alias = "iva")]
+    IvaNeto(ReportArgs),
+    /// ISR retenido.
+    #[command(visible_alias = "isr")]
+    IsrRetenido(ReportArgs),
+    /// ISR trasladado menos ISR retenido.
+    IsrNeto(ReportArgs),
+    /// IEPS trasladado.
+    IepsTrasladado(ReportArgs),
+    /// IEPS retenido.
+    IepsRetenido(ReportArgs),
+    /// IEPS trasladado menos IEPS retenido.
+    #[command(visible_alias = "ieps")]
+    IepsNeto(ReportArgs),
+    /// Percepciones de los recibos de nómina; imprime antes del resultado el desglose de
+    /// percepciones gravadas y exentas, ISR retenido y subsidio por patrón y mes de pago.
+    Nomina(ReportArgs),
+    /// Impuestos locales trasladados menos retenidos.
+    ImpuestosLocales(ReportArgs),
+    /// Impuestos locales trasladados, como el de hospedaje.
+    ImpuestosLocalesTrasladados(ReportArgs),
+    /// Impuestos locales retenidos, como los cedulares.
+    ImpuestosLocalesRetenidos(ReportArgs),
 }
 
 impl ReportTypeSubCommand {
@@ -78,8 +233,19 @@ impl ReportTypeSubCommand {
         match self {
             ReportTypeSubCommand::Total(arg) => arg,
             ReportTypeSubCommand::Subtotal(arg) => arg,
-            ReportTypeSubCommand::Iva(arg) => arg,
-            ReportTypeSubCommand::Isr(arg) => arg,
+            ReportTypeSubCommand::Descuento(arg) => arg,
+            ReportTypeSubCommand::IvaTrasladado(arg) => arg,
+            ReportTypeSubCommand::IvaRetenido(arg) => arg,
+            ReportTypeSubCommand::IvaNeto(arg) => arg,
+            ReportTypeSubCommand::IsrRetenido(arg) => arg,
+            ReportTypeSubCommand::IsrNeto(arg) => arg,
+            ReportTypeSubCommand::IepsTrasladado(arg) => arg,
+            ReportTypeSubCommand::IepsRetenido(arg) => arg,
+            ReportTypeSubCommand::IepsNeto(arg) => arg,
+            ReportTypeSubCommand::Nomina(arg) => arg,
+            ReportTypeSubCommand::ImpuestosLocales(arg) => arg,
+            ReportTypeSubCommand::ImpuestosLocalesTrasladados(arg) => arg,
+            ReportTypeSubCommand::ImpuestosLocalesRetenidos(arg) => arg,
         }
     }
 }
@@ -98,6 +264,48 @@ pub struct ReportArgs {
     #[clap(long, short = 'U')]
     pub unformatted: bool,
 
+    /// Flujo de efectivo: las facturas PUE se acumulan en su fecha de emisión y las PPD en las
+    /// fechas de sus complementos de pago, con el tipo de cambio del pago.
+    #[clap(long = "flujo")]
+    pub cash_basis: bool,
+
+    /// Excluye las facturas sustituidas por otro comprobante (relación 04).
+    #[clap(long = "excluir-sustituidas")]
+    pub exclude_substituted: bool,
+
+    /// Acumula las notas de crédito (relación 01) en la fecha de la factura que modifican,
+    /// solo sin `--flujo`.
+    #[clap(long = "netear-notas")]
+    pub net_credit_notes: bool,
+
+    /// Imprime antes del resultado el desglose indicado.
+    #[clap(long, value_enum)]
+    pub by: Option<Breakdown>,
+
+    /// Imprime antes del resultado el desglose por moneda original y su equivalente en MXN.
+    #[clap(long = "por-moneda")]
+    pub by_currency: bool,
+
+    /// Solo se incluirán los comprobantes de este tipo, puede indicarse varias veces.
+    #[clap(long = "tipo", value_enum)]
+    pub receipt_types: Vec<ReceiptType>,
+
+    /// Archivo con los UUID de las facturas canceladas: metadatos de la descarga masiva del
+    /// SAT, o CSV/TXT con un UUID por línea y opcionalmente su estatus.
+    #[clap(long = "canceladas", value_name = "FILE")]
+    pub cancellations: Option<PathBuf>,
+
+    /// Termina con error si algún archivo no pudo leerse como CFDI.
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Hilos para leer las facturas en paralelo, por defecto uno por núcleo.
+    #[clap(long, short = 'j', default_value_t = 0, hide_default_value = true)]
+    pub jobs: usize,
+
+    #[command(flatten)]
+    pub dedup: DedupArgs,
+
     #[clap(value_parser)]
     /// Carpeta desde donde se extraerán recursivamente las facturas.
     pub path: Option<PathBuf>,
@@ -113,4 +321,38 @@ impl ReportArgs {
     pub fn formatted(&self) -> bool {
         !self.unformatted
     }
+
+    pub fn cancellations(&self) -> Result<Option<Arc<CancellationList>>, SatrError> {
+        load_cancellations(self.cancellations.as_ref())
+    }
+}
+
+pub fn load_cancellations(
+    path: Option<&PathBuf>,
+) -> Result<Option<Arc<CancellationList>>, SatrError> {
+    path.map(|p| CancellationList::from_path(p).map(Arc::new))
+        .transpose()
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum Breakdown {
+    /// Cantidades, importes e impuestos por clave de producto o servicio.
+    Concepto,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct DedupArgs {
+    /// Incluye las facturas con UUID repetido en lugar de descartarlas.
+    #[clap(long)]
+    pub no_dedup: bool,
+
+    /// Imprime en stderr las facturas descartadas por tener un UUID repetido.
+    #[clap(long)]
+    pub duplicates: bool,
+}
+
+impl DedupArgs {
+    pub fn deduplicate(&self) -> bool {
+        !self.no_dedup
+    }
 }
@@ -0,0 +1,149 @@
+use std::{env, path::PathBuf};
+
+use clap::Parser;
+use rust_decimal::Decimal;
+use Lotus::Lotus;
+
+use crate::{
+    cli::{exit_with_error, print_skipped},
+    parse_dec,
+    reporter::RetentionTotal,
+    retention::Retention,
+    retention_extractor::{self, RetentionExtraction, RetentionExtractor},
+    xml_extractor::Origin,
+};
+
+use super::report;
+
+#[derive(Debug, Parser)]
+pub struct RetentionsSubCommand {
+    /// RFC al que se le retuvieron los impuestos.
+    #[clap(value_name = "RFC")]
+    pub rfc: String,
+
+    /// Solo se incluirán las constancias de este ejercicio.
+    #[clap(long = "ejercicio", value_name = "YEAR")]
+    pub fiscal_year: Option<i32>,
+
+    /// Imprime solo los números resultantes.
+    #[clap(long, short = 'U')]
+    pub unformatted: bool,
+
+    /// Termina con error si algún archivo no pudo leerse como constancia.
+    #[clap(long)]
+    pub strict: bool,
+
+    #[command(flatten)]
+    pub dedup: report::DedupArgs,
+
+    #[clap(value_name = "PATH")]
+    /// Carpeta desde donde se extraerán recursivamente las constancias.
+    pub path: Option<PathBuf>,
+}
+
+impl RetentionsSubCommand {
+    #[inline]
+    fn extractor(&self) -> RetentionExtractor {
+        RetentionExtractor::new(retention_extractor::Config {
+            recipient_rfc: self.rfc.clone(),
+            fiscal_year: self.fiscal_year,
+            deduplicate: self.dedup.deduplicate(),
+        })
+    }
+
+    #[inline]
+    fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| env::current_dir().expect("Error al leer el directorio actual"))
+    }
+
+    pub fn run(&self) {
+        let RetentionExtraction {
+            retentions,
+            duplicates,
+            skipped,
+        } = self
+            .extractor()
+            .extract(self.path())
+            .unwrap_or_else(|e| exit_with_error(e));
+
+        print_skipped(&skipped, self.strict);
+
+        if self.dedup.duplicates {
+            Self::print_duplicates(&duplicates);
+        }
+
+        let totals = RetentionTotal::by_retainer(&retentions);
+        let isr: Decimal = totals.iter().map(|t| t.isr_withheld).sum();
+        let iva: Decimal = totals.iter().map(|t| t.iva_withheld).sum();
+
+        if self.unformatted {
+            for t in &totals {
+                println!(
+                    "{} {} {} {} {} {} {}",
+                    t.retainer_rfc,
+                    t.documents,
+                    t.operation,
+                    t.taxed,
+                    t.exempt,
+                    t.isr_withheld,
+                    t.iva_withheld
+                );
+            }
+            println!("{isr} {iva}");
+
+            return;
+        }
+
+        let formatter = Lotus::new("$", 2);
+        let format = |n: Decimal| formatter.format(parse_dec!(n));
+
+        println!("------------------------------------------------------------------------------------------------------------------------------");
+        println!("Retenedor     | Constancias | Operaciones      | Gravado          | Exento           | ISR retenido     | IVA retenido     | Nombre");
+        println!("------------------------------------------------------------------------------------------------------------------------------");
+        for t in &totals {
+            println!(
+                "{:13} | {:>11} | {:>16} | {:>16} | {:>16} | {:>16} | {:>16} | {}",
+                t.retainer_rfc,
+                t.documents,
+                format(t.operation),
+                format(t.taxed),
+                format(t.exempt),
+                format(t.isr_withheld),
+                format(t.iva_withheld),
+                t.retainer_name.as_deref().unwrap_or_default(),
+            );
+        }
+        println!();
+        println!("ISR retenido: {}", format(isr));
+        println!("IVA retenido: {}", format(iva));
+
+        let iva_carried_forward: Decimal = totals.iter().map(|t| t.iva_carried_forward).sum();
+        let platform_fees: Decimal = totals.iter().map(|t| t.platform_fees).sum();
+        if !iva_carried_forward.is_zero() || !platform_fees.is_zero() {
+            println!(
+                "IVA trasladado en plataformas: {}",
+                format(iva_carried_forward)
+            );
+            println!("Comisiones de plataformas: {}", format(platform_fees));
+        }
+    }
+
+    fn print_duplicates(duplicates: &[Retention]) {
+        eprintln!("Constancias duplicadas descartadas: {}", duplicates.len());
+        for retention in duplicates {
+            eprintln!(
+                "  {} | {} | {} | {}",
+                retention.uuid().unwrap_or_default(),
+                retention.date,
+                retention.retainer.rfc,
+                retention
+                    .source
+                    .as_ref()
+                    .map(Origin::to_string)
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
@@ -0,0 +1,491 @@
+pub mod fiel;
+pub mod soap;
+
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{NaiveDateTime, Utc};
+
+use crate::{cli::report::SubjectType, error::SatrError, xml_extractor::archives};
+
+use self::fiel::Fiel;
+
+/// Código de las respuestas aceptadas por el SAT.
+const ACCEPTED: &str = "5000";
+
+/// Servidor del SAT para autenticar, solicitar y verificar las descargas.
+const SAT_REQUEST_HOST: &str = "https://cfdidescargamasivasolicitud.clouda.sat.gob.mx";
+
+/// Servidor del SAT para descargar los paquetes.
+const SAT_DOWNLOAD_HOST: &str = "https://cfdidescargamasiva.clouda.sat.gob.mx";
+
+const AUTH_PATH: &str = "/Autenticacion/Autenticacion.svc";
+const REQUEST_PATH: &str = "/SolicitaDescargaService.svc";
+const VERIFY_PATH: &str = "/VerificaSolicitudDescargaService.svc";
+const DOWNLOAD_PATH: &str = "/DescargaMasivaService.svc";
+
+/// URL de los servicios de la descarga masiva.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    pub auth: String,
+    pub request: String,
+    pub verify: String,
+    pub download: String,
+}
+
+impl Endpoints {
+    /// Servicios del SAT.
+    pub fn sat() -> Self {
+        Endpoints {
+            auth: format!("{SAT_REQUEST_HOST}{AUTH_PATH}"),
+            request: format!("{SAT_REQUEST_HOST}{REQUEST_PATH}"),
+            verify: format!("{SAT_REQUEST_HOST}{VERIFY_PATH}"),
+            download: format!("{SAT_DOWNLOAD_HOST}{DOWNLOAD_PATH}"),
+        }
+    }
+
+    /// Los cuatro servicios en un mismo servidor, con las rutas del SAT. Permite usar un
+    /// servidor de pruebas o un proxy.
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+
+        Endpoints {
+            auth: format!("{base}{AUTH_PATH}"),
+            request: format!("{base}{REQUEST_PATH}"),
+            verify: format!("{base}{VERIFY_PATH}"),
+            download: format!("{base}{DOWNLOAD_PATH}"),
+        }
+    }
+}
+
+/// Contenido de los paquetes solicitados.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestType {
+    /// Los xml de las facturas.
+    Cfdi,
+
+    /// Los metadatos de las facturas, incluyendo las canceladas.
+    Metadata,
+}
+
+impl Display for RequestType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestType::Cfdi => write!(f, "CFDI"),
+            RequestType::Metadata => write!(f, "Metadata"),
+        }
+    }
+}
+
+/// Estado de una solicitud según el servicio de verificación.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestState {
+    Aceptada,
+    EnProceso,
+    Terminada,
+    Error,
+    Rechazada,
+    Vencida,
+}
+
+impl RequestState {
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "1" => Some(Self::Aceptada),
+            "2" => Some(Self::EnProceso),
+            "3" => Some(Self::Terminada),
+            "4" => Some(Self::Error),
+            "5" => Some(Self::Rechazada),
+            "6" => Some(Self::Vencida),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_pending(&self) -> bool {
+        matches!(self, RequestState::Aceptada | RequestState::EnProceso)
+    }
+}
+
+#[derive(Debug)]
+pub struct Verification {
+    pub state: RequestState,
+    pub cfdis: u32,
+    pub packages: Vec<String>,
+    pub message: String,
+}
+
+/// Paquetes descargados de una solicitud terminada.
+#[derive(Debug)]
+pub struct Download {
+    /// Facturas o registros de metadatos incluidos en los paquetes, según el SAT.
+    pub cfdis: u32,
+    pub packages: Vec<PathBuf>,
+
+    /// Metadatos extraídos de cada paquete, solo en las solicitudes de metadatos.
+    pub metadata: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// RFC de la e.firma, que solicita la descarga.
+    pub rfc: String,
+
+    /// Descarga las facturas emitidas o recibidas por el RFC.
+    pub subject: SubjectType,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub request_type: RequestType,
+    pub endpoints: Endpoints,
+
+    /// Tiempo entre cada verificación de la solicitud.
+    pub poll_interval: Duration,
+
+    /// Tiempo máximo de espera a que el SAT termine la solicitud.
+    pub timeout: Duration,
+
+    /// Carpeta donde se guardan los paquetes.
+    pub output: PathBuf,
+}
+
+/// Cliente del servicio de descarga masiva del SAT. Cada paquete descargado es un zip que
+/// se guarda en la carpeta de salida, donde [`XmlExtractor`] lo encuentra como cualquier
+/// otro.
+///
+/// [`XmlExtractor`]: crate::xml_extractor::XmlExtractor
+pub struct Downloader {
+    config: Config,
+    fiel: Fiel,
+    agent: ureq::Agent,
+}
+
+impl Downloader {
+    pub fn new(config: Config, fiel: Fiel) -> Self {
+        Downloader {
+            config,
+            fiel,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    /// Obtiene un token de autenticación, válido por cinco minutos.
+    pub fn authenticate(&self) -> Result<String, SatrError> {
+        let body = soap::authenticate(&self.fiel, Utc::now())?;
+        let response = self.post(&self.config.endpoints.auth, soap::AUTH_ACTION, None, &body)?;
+
+        soap::find(&response, "AutenticaResult")
+            .map(|result| result.text)
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| Self::unexpected(&self.config.endpoints.auth))
+    }
+
+    /// Solicita la descarga y devuelve el identificador de la solicitud.
+    pub fn request(&self) -> Result<String, SatrError> {
+        let config = &self.config;
+        let format = |date: NaiveDateTime| date.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let (start, end) = (format(config.start), format(config.end));
+        let request_type = config.request_type.to_string();
+
+        let mut attributes = vec![
+            ("FechaInicial", start.as_str()),
+            ("FechaFinal", end.as_str()),
+            ("RfcSolicitante", config.rfc.as_str()),
+            ("TipoSolicitud", request_type.as_str()),
+        ];
+        let operation = match config.subject {
+            SubjectType::Emisor => {
+                attributes.push(("RfcEmisor", config.rfc.as_str()));
+                "SolicitaDescargaEmitidos"
+            }
+            SubjectType::Receptor => {
+                attributes.push(("RfcReceptor", config.rfc.as_str()));
+                // El SAT no entrega los xml de las facturas recibidas canceladas.
+                if config.request_type == RequestType::Cfdi {
+                    attributes.push(("EstadoComprobante", "Vigente"));
+                }
+                "SolicitaDescargaRecibidos"
+            }
+        };
+
+        let endpoint = &config.endpoints.request;
+        let response = self.post_signed(
+            endpoint,
+            &format!("{}/ISolicitaDescargaService/{operation}", soap::DOWNLOAD_NS),
+            operation,
+            "solicitud",
+            &attributes,
+        )?;
+        let result = soap::find(&response, &format!("{operation}Result"))
+            .ok_or_else(|| Self::unexpected(endpoint))?;
+        Self::check_status(&result)?;
+
+        result
+            .attribute("IdSolicitud")
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| Self::unexpected(endpoint))
+    }
+
+    pub fn verify(&self, request_id: &str) -> Result<Verification, SatrError> {
+        let endpoint = &self.config.endpoints.verify;
+        let response = self.post_signed(
+            endpoint,
+            &format!(
+                "{}/IVerificaSolicitudDescargaService/VerificaSolicitudDescarga",
+                soap::DOWNLOAD_NS
+            ),
+            "VerificaSolicitudDescarga",
+            "solicitud",
+            &[
+                ("IdSolicitud", request_id),
+                ("RfcSolicitante", &self.config.rfc),
+            ],
+        )?;
+
+        let result = soap::find(&response, "VerificaSolicitudDescargaResult")
+            .ok_or_else(|| Self::unexpected(endpoint))?;
+        Self::check_status(&result)?;
+
+        let state = result
+            .attribute("EstadoSolicitud")
+            .and_then(RequestState::from_code)
+            .ok_or_else(|| Self::unexpected(endpoint))?;
+
+        Ok(Verification {
+            state,
+            cfdis: result
+                .attribute("NumeroCFDIs")
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_default(),
+            packages: soap::find_all(&response, "IdsPaquetes")
+                .into_iter()
+                .map(|p| p.text.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            message: result.attribute("Mensaje").unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Descarga un paquete como `<IdPaquete>.zip` en la carpeta de salida. El zip se
+    /// escribe con otro nombre y se renombra al terminar, por lo que si existe está completo.
+    pub fn download_package(&self, package_id: &str) -> Result<PathBuf, SatrError> {
+        let endpoint = &self.config.endpoints.download;
+        let response = self.post_signed(
+            endpoint,
+            &format!(
+                "{}/IDescargaMasivaTercerosService/Descargar",
+                soap::DOWNLOAD_NS
+            ),
+            "PeticionDescargaMasivaTercerosEntrada",
+            "peticionDescarga",
+            &[
+                ("IdPaquete", package_id),
+                ("RfcSolicitante", &self.config.rfc),
+            ],
+        )?;
+
+        let status =
+            soap::find(&response, "respuesta").ok_or_else(|| Self::unexpected(endpoint))?;
+        Self::check_status(&status)?;
+
+        let package = soap::find(&response, "Paquete").ok_or_else(|| Self::unexpected(endpoint))?;
+        let encoded: String = package
+            .text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let contents = STANDARD
+            .decode(encoded)
+            .map_err(|_| Self::unexpected(endpoint))?;
+
+        let output = &self.config.output;
+        let path = self.package_path(package_id)?;
+        fs::create_dir_all(output).map_err(|e| SatrError::Io(output.clone(), e))?;
+        let tmp = path.with_extension("zip.tmp");
+        fs::write(&tmp, contents).map_err(|e| SatrError::Io(tmp.clone(), e))?;
+        fs::rename(&tmp, &path).map_err(|e| SatrError::Io(path.clone(), e))?;
+
+        Ok(path)
+    }
+
+    /// Espera a que el SAT termine la solicitud y descarga sus paquetes. Los paquetes que
+    /// ya existen en la carpeta de salida no se vuelven a descargar. Los metadatos se
+    /// extraen además como `<IdPaquete>.txt`, que es lo que leen `satr reconcile` y
+    /// `--canceladas`.
+    pub fn download(&self, request_id: &str) -> Result<Download, SatrError> {
+        let started = Instant::now();
+
+        let verification = loop {
+            let verification = self.verify(request_id)?;
+            if !verification.state.is_pending() {
+                break verification;
+            }
+
+            if started.elapsed() + self.config.poll_interval > self.config.timeout {
+                return Err(SatrError::RequestPending(request_id.to_string()));
+            }
+            thread::sleep(self.config.poll_interval);
+        };
+
+        if verification.state != RequestState::Terminada {
+            return Err(SatrError::Sat {
+                code: format!("{:?}", verification.state),
+                message: verification.message,
+            });
+        }
+
+        let packages: Vec<PathBuf> = verification
+            .packages
+            .iter()
+            .map(|package| {
+                let path = self.package_path(package)?;
+                if path.is_file() {
+                    Ok(path)
+                } else {
+                    self.download_package(package)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let metadata = match self.config.request_type {
+            RequestType::Metadata => packages
+                .iter()
+                .map(|package| Self::extract_metadata(package))
+                .collect::<Result<_, _>>()?,
+            RequestType::Cfdi => Vec::new(),
+        };
+
+        Ok(Download {
+            cfdis: verification.cfdis,
+            packages,
+            metadata,
+        })
+    }
+
+    /// Ruta del zip de un paquete. El identificador viene de la respuesta del servicio, por
+    /// lo que se rechaza si no es un nombre de archivo simple.
+    fn package_path(&self, package_id: &str) -> Result<PathBuf, SatrError> {
+        let valid = !package_id.is_empty()
+            && package_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(SatrError::Http(
+                self.config.endpoints.verify.clone(),
+                format!("identificador de paquete inválido: {package_id}"),
+            ));
+        }
+
+        Ok(self.config.output.join(format!("{package_id}.zip")))
+    }
+
+    /// Extrae los `.txt` de un paquete de metadatos en un solo archivo junto al zip, con
+    /// el mismo nombre. Si ya existe no se vuelve a extraer.
+    fn extract_metadata(package: &Path) -> Result<PathBuf, SatrError> {
+        let path = package.with_extension("txt");
+        if path.is_file() {
+            return Ok(path);
+        }
+
+        let file = File::open(package).map_err(|e| SatrError::Io(package.to_path_buf(), e))?;
+        let mut archive =
+            zip::ZipArchive::new(file).map_err(|e| SatrError::Zip(package.to_path_buf(), e))?;
+        let tmp = path.with_extension("txt.tmp");
+        let mut output = File::create(&tmp).map_err(|e| SatrError::Io(tmp.clone(), e))?;
+
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|e| SatrError::Zip(package.to_path_buf(), e))?;
+            if !archives::has_extension(entry.name(), ".txt") {
+                continue;
+            }
+
+            let name = entry.name().to_string();
+            io::copy(&mut entry, &mut output)
+                .map_err(|e| SatrError::Io(archives::entry_path(package, &name), e))?;
+        }
+        fs::rename(&tmp, &path).map_err(|e| SatrError::Io(path.clone(), e))?;
+
+        Ok(path)
+    }
+
+    /// Envía una operación firmada, autenticándose antes para no depender de la vigencia
+    /// del token.
+    fn post_signed(
+        &self,
+        endpoint: &str,
+        action: &str,
+        operation: &str,
+        element: &str,
+        attributes: &[(&str, &str)],
+    ) -> Result<String, SatrError> {
+        let token = self.authenticate()?;
+        let body = soap::signed_operation(&self.fiel, operation, element, attributes)?;
+
+        self.post(endpoint, action, Some(&token), &body)
+    }
+
+    fn post(
+        &self,
+        endpoint: &str,
+        action: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> Result<String, SatrError> {
+        let http_error = |e: String| SatrError::Http(endpoint.to_string(), e);
+        let mut request = self
+            .agent
+            .post(endpoint)
+            .set("Content-Type", "text/xml; charset=utf-8")
+            .set("SOAPAction", action);
+        if let Some(token) = token {
+            request = request.set("Authorization", &format!(r#"WRAP access_token="{token}""#));
+        }
+
+        // Los errores de SOAP se responden con estatus 500 y el detalle en el cuerpo.
+        let response = match request.send_string(body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(http_error(e.to_string())),
+        };
+        let status = response.status();
+        let mut contents = String::new();
+        response
+            .into_reader()
+            .read_to_string(&mut contents)
+            .map_err(|e| http_error(e.to_string()))?;
+
+        if let Some(fault) = soap::find(&contents, "faultstring") {
+            return Err(SatrError::Sat {
+                code: status.to_string(),
+                message: fault.text,
+            });
+        }
+        if status >= 400 {
+            return Err(http_error(format!("estatus {status}")));
+        }
+
+        Ok(contents)
+    }
+
+    fn check_status(result: &soap::ResponseElement) -> Result<(), SatrError> {
+        match result.attribute("CodEstatus") {
+            Some(ACCEPTED) => Ok(()),
+            code => Err(SatrError::Sat {
+                code: code.unwrap_or_default().to_string(),
+                message: result.attribute("Mensaje").unwrap_or_default().to_string(),
+            }),
+        }
+    }
+
+    #[inline]
+    fn unexpected(endpoint: &str) -> SatrError {
+        SatrError::Http(endpoint.to_string(), "respuesta inesperada".to_string())
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pkcs8::DecodePrivateKey;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPrivateKey};
+use sha1::{Digest, Sha1};
+use x509_cert::{der::Decode, Certificate};
+
+use crate::error::SatrError;
+
+/// e.firma del contribuyente: certificado (.cer) y llave privada (.key) emitidos por el
+/// SAT, usados para autenticarse y firmar las solicitudes de descarga.
+pub struct Fiel {
+    /// Certificado codificado en DER.
+    certificate: Vec<u8>,
+    key: RsaPrivateKey,
+
+    /// Archivo de la llave privada, solo se usa en los errores.
+    key_path: PathBuf,
+    issuer_name: String,
+
+    /// Número de serie del certificado en decimal.
+    serial_number: String,
+}
+
+impl Fiel {
+    /// Lee el certificado en DER y la llave privada PKCS#8 cifrada con la contraseña.
+    pub fn from_files(certificate: &Path, key: &Path, password: &str) -> Result<Self, SatrError> {
+        let der = fs::read(certificate).map_err(|e| SatrError::Io(certificate.to_path_buf(), e))?;
+        let parsed = Certificate::from_der(&der)
+            .map_err(|e| SatrError::Fiel(certificate.to_path_buf(), e.to_string()))?;
+
+        let key_path = key.to_path_buf();
+        let key_der = fs::read(key).map_err(|e| SatrError::Io(key_path.clone(), e))?;
+        let key = RsaPrivateKey::from_pkcs8_encrypted_der(&key_der, password).map_err(|_| {
+            SatrError::Fiel(
+                key_path.clone(),
+                "no se pudo descifrar la llave privada, revisa la contraseña".to_string(),
+            )
+        })?;
+
+        let tbs = &parsed.tbs_certificate;
+        Ok(Fiel {
+            issuer_name: tbs.issuer.to_string(),
+            serial_number: BigUint::from_bytes_be(tbs.serial_number.as_bytes()).to_string(),
+            certificate: der,
+            key,
+            key_path,
+        })
+    }
+
+    #[inline]
+    pub fn certificate(&self) -> String {
+        STANDARD.encode(&self.certificate)
+    }
+
+    #[inline]
+    pub fn issuer_name(&self) -> &str {
+        &self.issuer_name
+    }
+
+    #[inline]
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    /// Firma RSA-SHA1 en base64.
+    pub fn sign(&self, data: &[u8]) -> Result<String, SatrError> {
+        let hashed = Sha1::digest(data);
+        let signature = self
+            .key
+            .sign(Pkcs1v15Sign::new::<Sha1>(), &hashed)
+            .map_err(|e| {
+                SatrError::Fiel(self.key_path.clone(), format!("no se pudo firmar: {e}"))
+            })?;
+
+        Ok(STANDARD.encode(signature))
+    }
+}
+
+/// Digesto SHA1 en base64.
+pub fn digest(data: &[u8]) -> String {
+    STANDARD.encode(Sha1::digest(data))
+}
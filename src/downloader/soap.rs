@@ -0,0 +1,233 @@
+//! Mensajes SOAP del servicio de descarga masiva. Los elementos firmados se escriben ya en
+//! su forma canónica (c14n exclusiva: atributos ordenados y sin elementos vacíos abreviados),
+//! por lo que el digesto se calcula directamente sobre el texto enviado.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::fiel::{self, Fiel};
+use crate::error::SatrError;
+
+const SOAP_NS: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const WSU_NS: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd";
+const WSSE_NS: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd";
+const X509_TOKEN: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-x509-token-profile-1.0#X509v3";
+const BASE64_ENCODING: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-soap-message-security-1.0#Base64Binary";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+/// Espacio de nombres del servicio de autenticación.
+const AUTH_NS: &str = "http://DescargaMasivaTerceros.gob.mx";
+
+/// Espacio de nombres de los servicios de solicitud, verificación y descarga.
+pub const DOWNLOAD_NS: &str = "http://DescargaMasivaTerceros.sat.gob.mx";
+
+pub const AUTH_ACTION: &str = "http://DescargaMasivaTerceros.gob.mx/IAutenticacion/Autentica";
+
+/// Vigencia del token de autenticación.
+const TOKEN_LIFETIME: i64 = 5;
+
+/// Solicitud de autenticación, firmada sobre el `Timestamp` con el certificado como token.
+pub fn authenticate(fiel: &Fiel, now: DateTime<Utc>) -> Result<String, SatrError> {
+    let format = |date: DateTime<Utc>| date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let created = format(now);
+    let expires = format(now + Duration::minutes(TOKEN_LIFETIME));
+    let token_id = format!("uuid-{}-1", now.timestamp_nanos_opt().unwrap_or_default());
+
+    let timestamp_content =
+        format!("<u:Created>{created}</u:Created><u:Expires>{expires}</u:Expires>");
+    let digest = fiel::digest(
+        format!(r#"<u:Timestamp xmlns:u="{WSU_NS}" u:Id="_0">{timestamp_content}</u:Timestamp>"#)
+            .as_bytes(),
+    );
+    let signed_info = signed_info("#_0", EXC_C14N, &digest);
+    let signature_value = fiel.sign(signed_info.as_bytes())?;
+
+    Ok(format!(
+        concat!(
+            r#"<s:Envelope xmlns:s="{soap}" xmlns:u="{wsu}">"#,
+            r#"<s:Header><o:Security xmlns:o="{wsse}" s:mustUnderstand="1">"#,
+            r#"<u:Timestamp u:Id="_0">{timestamp}</u:Timestamp>"#,
+            r#"<o:BinarySecurityToken u:Id="{token_id}" ValueType="{x509}" EncodingType="{base64}">{certificate}</o:BinarySecurityToken>"#,
+            r#"<Signature xmlns="{dsig}">{signed_info}<SignatureValue>{signature_value}</SignatureValue>"#,
+            r##"<KeyInfo><o:SecurityTokenReference><o:Reference ValueType="{x509}" URI="#{token_id}"/></o:SecurityTokenReference></KeyInfo>"##,
+            r#"</Signature></o:Security></s:Header>"#,
+            r#"<s:Body><Autentica xmlns="{auth}"/></s:Body></s:Envelope>"#
+        ),
+        soap = SOAP_NS,
+        wsu = WSU_NS,
+        wsse = WSSE_NS,
+        timestamp = timestamp_content,
+        token_id = token_id,
+        x509 = X509_TOKEN,
+        base64 = BASE64_ENCODING,
+        certificate = fiel.certificate(),
+        dsig = DSIG_NS,
+        signed_info = signed_info,
+        signature_value = signature_value,
+        auth = AUTH_NS,
+    ))
+}
+
+/// Operación de los servicios de descarga: un elemento `des:<operation>` con un hijo
+/// `des:<element>` que lleva los atributos y la firma envolvente de la e.firma.
+pub fn signed_operation(
+    fiel: &Fiel,
+    operation: &str,
+    element: &str,
+    attributes: &[(&str, &str)],
+) -> Result<String, SatrError> {
+    let mut attributes = attributes.to_vec();
+    attributes.sort_by_key(|(name, _)| *name);
+    let attributes: String = attributes
+        .iter()
+        .map(|(name, value)| format!(r#" {name}="{}""#, escape(value)))
+        .collect();
+
+    let digest = fiel::digest(
+        format!(
+            r#"<des:{operation} xmlns:des="{DOWNLOAD_NS}"><des:{element}{attributes}></des:{element}></des:{operation}>"#
+        )
+        .as_bytes(),
+    );
+    let signed_info = signed_info("", ENVELOPED, &digest);
+    let signature_value = fiel.sign(signed_info.as_bytes())?;
+    let signature = format!(
+        concat!(
+            r#"<Signature xmlns="{dsig}">{signed_info}<SignatureValue>{signature_value}</SignatureValue>"#,
+            "<KeyInfo><X509Data><X509IssuerSerial><X509IssuerName>{issuer}</X509IssuerName>",
+            "<X509SerialNumber>{serial}</X509SerialNumber></X509IssuerSerial>",
+            "<X509Certificate>{certificate}</X509Certificate></X509Data></KeyInfo></Signature>"
+        ),
+        dsig = DSIG_NS,
+        signed_info = signed_info,
+        signature_value = signature_value,
+        issuer = escape_text(fiel.issuer_name()),
+        serial = fiel.serial_number(),
+        certificate = fiel.certificate(),
+    );
+
+    Ok(format!(
+        concat!(
+            r#"<s:Envelope xmlns:s="{soap}" xmlns:des="{des}"><s:Header/><s:Body>"#,
+            "<des:{operation}><des:{element}{attributes}>{signature}</des:{element}></des:{operation}>",
+            "</s:Body></s:Envelope>"
+        ),
+        soap = SOAP_NS,
+        des = DOWNLOAD_NS,
+        operation = operation,
+        element = element,
+        attributes = attributes,
+        signature = signature,
+    ))
+}
+
+fn signed_info(uri: &str, transform: &str, digest: &str) -> String {
+    format!(
+        concat!(
+            r#"<SignedInfo xmlns="{dsig}">"#,
+            r#"<CanonicalizationMethod Algorithm="{c14n}"></CanonicalizationMethod>"#,
+            r#"<SignatureMethod Algorithm="{dsig}rsa-sha1"></SignatureMethod>"#,
+            r#"<Reference URI="{uri}"><Transforms><Transform Algorithm="{transform}"></Transform></Transforms>"#,
+            r#"<DigestMethod Algorithm="{dsig}sha1"></DigestMethod><DigestValue>{digest}</DigestValue>"#,
+            "</Reference></SignedInfo>"
+        ),
+        dsig = DSIG_NS,
+        c14n = EXC_C14N,
+        uri = uri,
+        transform = transform,
+        digest = digest,
+    )
+}
+
+/// Escapa un texto como en la forma canónica.
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\r', "&#xD;")
+}
+
+/// Escapa un valor de atributo como en la forma canónica.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('"', "&quot;")
+        .replace('\t', "&#x9;")
+        .replace('\n', "&#xA;")
+        .replace('\r', "&#xD;")
+}
+
+/// Elemento de una respuesta, sin importar su prefijo.
+#[derive(Debug, Default)]
+pub struct ResponseElement {
+    pub attributes: HashMap<String, String>,
+    pub text: String,
+}
+
+impl ResponseElement {
+    #[inline]
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+}
+
+/// Elementos de una respuesta con el nombre local indicado, en orden.
+pub fn find_all(xml: &str, local_name: &str) -> Vec<ResponseElement> {
+    let mut reader = Reader::from_str(xml);
+    let mut elements = Vec::new();
+    let mut current: Option<ResponseElement> = None;
+    let attributes = |e: &BytesStart| {
+        e.attributes()
+            .flatten()
+            .filter_map(|a| {
+                let name = String::from_utf8_lossy(a.key.local_name().as_ref()).to_string();
+                a.unescape_value().ok().map(|v| (name, v.to_string()))
+            })
+            .collect()
+    };
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) if e.local_name().as_ref() == local_name.as_bytes() => {
+                current = Some(ResponseElement {
+                    attributes: attributes(&e),
+                    text: String::new(),
+                });
+            }
+            Ok(Event::Empty(e)) if e.local_name().as_ref() == local_name.as_bytes() => {
+                elements.push(ResponseElement {
+                    attributes: attributes(&e),
+                    text: String::new(),
+                });
+            }
+            Ok(Event::Text(t)) => {
+                if let (Some(element), Ok(text)) = (&mut current, t.unescape()) {
+                    element.text.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == local_name.as_bytes() => {
+                elements.extend(current.take());
+            }
+            Ok(Event::Eof) | Err(_) => return elements,
+            _ => {}
+        }
+    }
+}
+
+/// Primer elemento de una respuesta con el nombre local indicado.
+#[inline]
+pub fn find(xml: &str, local_name: &str) -> Option<ResponseElement> {
+    find_all(xml, local_name).into_iter().next()
+}
@@ -0,0 +1,113 @@
+use std::{fmt::Display, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum SatrError {
+    /// Error al leer un archivo o directorio.
+    Io(PathBuf, io::Error),
+
+    /// Error al recorrer el directorio de facturas.
+    Walk(walkdir::Error),
+
+    /// Error al abrir o leer un archivo zip.
+    Zip(PathBuf, zip::result::ZipError),
+
+    /// El elemento raíz del xml no es `cfdi:Comprobante`.
+    NotCfdi,
+
+    /// El XML no corresponde a un CFDI soportado.
+    Deserialize(quick_xml::DeError),
+
+    /// El CFDI no cumple con los atributos obligatorios de su versión.
+    InvalidBill(String),
+
+    /// Línea inválida en la lista de facturas canceladas.
+    CancellationList { path: PathBuf, line: usize },
+
+    /// Línea inválida en los metadatos de la descarga masiva.
+    Metadata { path: PathBuf, line: usize },
+
+    /// Error al leer o escribir el índice de facturas.
+    Index(PathBuf, serde_json::Error),
+
+    /// Error guardado en el índice de un archivo que no ha cambiado.
+    Indexed(String),
+
+    /// Certificado o llave privada de la e.firma inválidos.
+    Fiel(PathBuf, String),
+
+    /// Error al leer la contraseña de la llave privada.
+    Password(io::Error),
+
+    /// Error de conexión o respuesta inválida de un servicio web.
+    Http(String, String),
+
+    /// El servicio de descarga masiva rechazó la petición.
+    Sat { code: String, message: String },
+
+    /// La solicitud de descarga no terminó en el tiempo de espera.
+    RequestPending(String),
+
+    /// Combinación de opciones que no puede producir un reporte.
+    InvalidOptions(String),
+
+    /// Factura en moneda extranjera sin tipo de cambio.
+    MissingExchangeRate { uuid: String, currency: String },
+}
+
+impl Display for SatrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SatrError::Io(path, e) => write!(f, "Error al leer {}: {e}", path.display()),
+            SatrError::Walk(e) => write!(f, "Error al recorrer el directorio: {e}"),
+            SatrError::Zip(path, e) => write!(f, "Error al leer el zip {}: {e}", path.display()),
+            SatrError::NotCfdi => write!(f, "El xml no es un CFDI"),
+            SatrError::Deserialize(e) => write!(f, "Error al deserializar el CFDI: {e}"),
+            SatrError::InvalidBill(e) => write!(f, "CFDI inválido: {e}"),
+            SatrError::CancellationList { path, line } => write!(
+                f,
+                "Línea {line} inválida en la lista de canceladas {}",
+                path.display()
+            ),
+            SatrError::Metadata { path, line } => {
+                write!(
+                    f,
+                    "Línea {line} inválida en los metadatos {}",
+                    path.display()
+                )
+            }
+            SatrError::Index(path, e) => write!(
+                f,
+                "Índice inválido {}: {e}, puede reconstruirse con `satr index rebuild`",
+                path.display()
+            ),
+            SatrError::Indexed(e) => write!(f, "{e}"),
+            SatrError::Fiel(path, e) => write!(f, "e.firma inválida {}: {e}", path.display()),
+            SatrError::Password(e) => write!(f, "Error al leer la contraseña: {e}"),
+            SatrError::Http(url, e) => write!(f, "Error al consultar {url}: {e}"),
+            SatrError::Sat { code, message } => write!(f, "El SAT respondió {code}: {message}"),
+            SatrError::RequestPending(id) => write!(
+                f,
+                "La solicitud {id} sigue en proceso, puede continuarse con `--solicitud {id}`"
+            ),
+            SatrError::InvalidOptions(e) => write!(f, "Opciones inválidas: {e}"),
+            SatrError::MissingExchangeRate { uuid, currency } => write!(
+                f,
+                "La factura {uuid} está en {currency} y no tiene TipoCambio"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SatrError {}
+
+impl From<walkdir::Error> for SatrError {
+    fn from(e: walkdir::Error) -> Self {
+        SatrError::Walk(e)
+    }
+}
+
+impl From<quick_xml::DeError> for SatrError {
+    fn from(e: quick_xml::DeError) -> Self {
+        SatrError::Deserialize(e)
+    }
+}
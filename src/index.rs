@@ -0,0 +1,467 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bill::Bill,
+    bill_extractor::SkippedFile,
+    error::SatrError,
+    parallel::OrderedMap,
+    xml_extractor::{DocumentKind, Origin, XmlExtractor},
+};
+
+/// Nombre del archivo del índice, dentro de la carpeta indexada.
+pub const INDEX_FILE: &str = ".satr-index.json";
+
+/// Cambia con el modelo de las facturas; los índices de otra versión se reconstruyen. Una
+/// prueba falla si el formato de las facturas cambia sin incrementarla.
+pub(crate) const INDEX_VERSION: u32 = 3;
+
+/// Facturas ya deserializadas de una carpeta, guardadas en disco para no volver a leer los
+/// archivos que no han cambiado. Los archivos se identifican por su ruta relativa, tamaño,
+/// fecha de modificación y hash de su contenido. También se guardan los errores de lectura,
+/// que se repiten sin volver a leer el archivo mientras no cambie.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedFile {
+    size: u64,
+    modified: SystemTime,
+    hash: u64,
+    results: Vec<IndexedResult>,
+}
+
+/// Factura de un archivo, o el mensaje del error con que no pudo leerse.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedResult {
+    /// Entrada del archivo comprimido que contiene la factura.
+    entry: Option<String>,
+    result: Result<Bill, String>,
+}
+
+impl IndexedResult {
+    fn new(result: Result<Bill, SkippedFile>) -> Self {
+        let entry = |origin| match origin {
+            Some(Origin::Archive { entry, .. }) => Some(entry),
+            _ => None,
+        };
+
+        match result {
+            Ok(mut bill) => IndexedResult {
+                entry: entry(bill.source.take()),
+                result: Ok(bill),
+            },
+            Err(SkippedFile { origin, error }) => IndexedResult {
+                entry: entry(Some(origin)),
+                result: Err(error.to_string()),
+            },
+        }
+    }
+
+    /// Devuelve la factura o el error con el origen dentro del archivo indexado.
+    fn into_result(self, path: &Path) -> Result<Bill, SkippedFile> {
+        let origin = match self.entry {
+            Some(entry) => Origin::Archive {
+                path: path.to_path_buf(),
+                entry,
+            },
+            None => Origin::File(path.to_path_buf()),
+        };
+
+        match self.result {
+            Ok(mut bill) => {
+                bill.source = Some(origin);
+                Ok(bill)
+            }
+            Err(message) => Err(SkippedFile {
+                origin,
+                error: SatrError::Indexed(message),
+            }),
+        }
+    }
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Index {
+            version: INDEX_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl Index {
+    #[inline]
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(INDEX_FILE)
+    }
+
+    #[inline]
+    pub fn exists(root: &Path) -> bool {
+        Self::path(root).is_file()
+    }
+
+    /// Lee el índice de una carpeta, o uno vacío si no existe o es de otra versión.
+    pub fn load(root: &Path) -> Result<Self, SatrError> {
+        let path = Self::path(root);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(SatrError::Io(path, e)),
+        };
+
+        let index: Index = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| SatrError::Index(path.clone(), e))?;
+        if index.version != INDEX_VERSION {
+            return Ok(Self::default());
+        }
+
+        Ok(index)
+    }
+
+    /// Guarda el índice reemplazando el anterior solo hasta que se escribió completo.
+    pub fn save(&self, root: &Path) -> Result<(), SatrError> {
+        let path = Self::path(root);
+        let tmp = path.with_extension("json.tmp");
+        let file = File::create(&tmp).map_err(|e| SatrError::Io(tmp.clone(), e))?;
+
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|e| SatrError::Index(tmp.clone(), e))?;
+        fs::rename(&tmp, &path).map_err(|e| SatrError::Io(path, e))
+    }
+
+    /// Elimina el índice de una carpeta, si existe.
+    pub fn remove(root: &Path) -> Result<(), SatrError> {
+        let path = Self::path(root);
+
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SatrError::Io(path, e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Actualiza el índice con los archivos nuevos o modificados y devuelve las facturas de
+    /// la carpeta en el mismo orden que [`XmlExtractor::iter_xmls`]. El índice solo se
+    /// escribe si cambió; si no puede escribirse, por ejemplo en una carpeta de solo
+    /// lectura, se avisa en stderr y se devuelven igualmente las facturas. Los archivos que
+    /// no pueden leerse se devuelven como [`SkippedFile`], solo falla si no puede leerse la
+    /// carpeta o el índice.
+    pub fn sync(root: &Path, jobs: usize) -> Result<Vec<Result<Bill, SkippedFile>>, SatrError> {
+        Self::sync_with(root, jobs, |e| {
+            eprintln!("No se pudo guardar el índice: {e}");
+            Ok(())
+        })
+    }
+
+    /// Crea de nuevo el índice leyendo todos los archivos, como [`Index::sync`] pero falla
+    /// si el índice no puede escribirse.
+    pub fn rebuild(root: &Path, jobs: usize) -> Result<Vec<Result<Bill, SkippedFile>>, SatrError> {
+        Self::remove(root)?;
+        Self::sync_with(root, jobs, Err)
+    }
+
+    fn sync_with(
+        root: &Path,
+        jobs: usize,
+        on_save_error: impl FnOnce(SatrError) -> Result<(), SatrError>,
+    ) -> Result<Vec<Result<Bill, SkippedFile>>, SatrError> {
+        let mut index = Self::load(root)?;
+        let files = scan(root)?;
+        let (changed, unreadable) = index.update(&files, jobs);
+        if changed || !Self::exists(root) {
+            index.save(root).or_else(on_save_error)?;
+        }
+
+        Ok(index.into_bills(files, unreadable))
+    }
+
+    /// Compara el índice con los archivos en disco sin leerlos.
+    pub fn status(root: &Path) -> Result<IndexStatus, SatrError> {
+        let index = Self::load(root)?;
+        let files = scan(root)?;
+        let results = index.files.values().flat_map(|f| &f.results);
+        let (bills, skipped): (Vec<_>, Vec<_>) = results.partition(|r| r.result.is_ok());
+        let mut status = IndexStatus {
+            files: index.files.len(),
+            bills: bills.len(),
+            skipped: skipped.len(),
+            ..Default::default()
+        };
+
+        for file in files.iter().flatten() {
+            match index.files.get(&file.key) {
+                Some(indexed) if file.matches(indexed) => {}
+                Some(_) => status.modified += 1,
+                None => status.unindexed += 1,
+            }
+        }
+        let keys: HashSet<&PathBuf> = files.iter().flatten().map(|f| &f.key).collect();
+        status.removed = index.files.keys().filter(|key| !keys.contains(key)).count();
+
+        Ok(status)
+    }
+
+    /// Lee los archivos que cambiaron. Devuelve si el índice cambió y los archivos que no
+    /// pudieron abrirse, que no se guardan en el índice.
+    fn update(
+        &mut self,
+        files: &[Result<DiskFile, SkippedFile>],
+        jobs: usize,
+    ) -> (bool, HashMap<PathBuf, SkippedFile>) {
+        let keys: HashSet<&PathBuf> = files.iter().flatten().map(|f| &f.key).collect();
+        let indexed = self.files.len();
+        self.files.retain(|key, _| keys.contains(key));
+        let mut changed = self.files.len() != indexed;
+
+        let modified = files
+            .iter()
+            .flatten()
+            .filter_map(|file| match self.files.get(&file.key) {
+                Some(indexed) if file.matches(indexed) => None,
+                Some(indexed) => Some((file, Some(indexed.hash))),
+                None => Some((file, None)),
+            });
+        let read: Vec<_> =
+            OrderedMap::new(modified, jobs, |(file, hash)| file.read(hash)).collect();
+
+        let mut unreadable = HashMap::new();
+        for (file, read) in read {
+            match read {
+                FileRead::Unchanged => {
+                    if let Some(indexed) = self.files.get_mut(&file.key) {
+                        indexed.size = file.size;
+                        indexed.modified = file.modified;
+                        changed = true;
+                    }
+                }
+                FileRead::Parsed { hash, results } => {
+                    self.files.insert(
+                        file.key.clone(),
+                        IndexedFile {
+                            size: file.size,
+                            modified: file.modified,
+                            hash,
+                            results: results.into_iter().map(IndexedResult::new).collect(),
+                        },
+                    );
+                    changed = true;
+                }
+                FileRead::Unreadable(skipped) => {
+                    changed |= self.files.remove(&file.key).is_some();
+                    unreadable.insert(file.key.clone(), skipped);
+                }
+            }
+        }
+
+        (changed, unreadable)
+    }
+
+    fn into_bills(
+        mut self,
+        files: Vec<Result<DiskFile, SkippedFile>>,
+        mut unreadable: HashMap<PathBuf, SkippedFile>,
+    ) -> Vec<Result<Bill, SkippedFile>> {
+        let mut bills = Vec::new();
+
+        for file in files {
+            let file = match file {
+                Ok(file) => file,
+                Err(skipped) => {
+                    bills.push(Err(skipped));
+                    continue;
+                }
+            };
+
+            if let Some(indexed) = self.files.remove(&file.key) {
+                bills.extend(
+                    indexed
+                        .results
+                        .into_iter()
+                        .map(|result| result.into_result(&file.path)),
+                );
+            } else if let Some(skipped) = unreadable.remove(&file.key) {
+                bills.push(Err(skipped));
+            }
+        }
+
+        bills
+    }
+}
+
+/// Diferencias entre el índice y los archivos en disco.
+#[derive(Debug, Default)]
+pub struct IndexStatus {
+    /// Archivos en el índice.
+    pub files: usize,
+    pub bills: usize,
+
+    /// Entradas que no pudieron leerse como CFDI, se reportan sin volver a leerlas.
+    pub skipped: usize,
+
+    /// Archivos nuevos o que no pudieron abrirse, que no están en el índice.
+    pub unindexed: usize,
+
+    /// Archivos cuyo tamaño o fecha de modificación cambió.
+    pub modified: usize,
+
+    /// Archivos indexados que ya no existen.
+    pub removed: usize,
+}
+
+impl IndexStatus {
+    #[inline]
+    pub fn is_up_to_date(&self) -> bool {
+        self.unindexed == 0 && self.modified == 0 && self.removed == 0
+    }
+}
+
+/// Archivo xml o comprimido de la carpeta indexada.
+struct DiskFile {
+    path: PathBuf,
+
+    /// Ruta relativa a la carpeta indexada.
+    key: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+enum FileRead {
+    /// El contenido coincide con el hash del índice.
+    Unchanged,
+
+    Parsed {
+        hash: u64,
+        results: Vec<Result<Bill, SkippedFile>>,
+    },
+
+    /// No se pudo leer el archivo para calcular su hash.
+    Unreadable(SkippedFile),
+}
+
+impl DiskFile {
+    #[inline]
+    fn matches(&self, indexed: &IndexedFile) -> bool {
+        self.size == indexed.size && self.modified == indexed.modified
+    }
+
+    fn new(root: &Path, entry: &walkdir::DirEntry) -> Result<Self, SatrError> {
+        let path = entry.path().to_path_buf();
+        let metadata = entry
+            .metadata()
+            .map_err(|e| SatrError::Io(path.clone(), e.into()))?;
+        let modified = metadata
+            .modified()
+            .map_err(|e| SatrError::Io(path.clone(), e))?;
+
+        Ok(DiskFile {
+            key: path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+            path,
+            size: metadata.len(),
+            modified,
+        })
+    }
+
+    /// Deserializa las facturas del archivo si su contenido no coincide con el hash.
+    fn read(&self, indexed_hash: Option<u64>) -> (&Self, FileRead) {
+        let hash = match hash_file(&self.path) {
+            Ok(hash) => hash,
+            Err(error) => {
+                let skipped = SkippedFile {
+                    origin: Origin::File(self.path.clone()),
+                    error,
+                };
+                return (self, FileRead::Unreadable(skipped));
+            }
+        };
+        if indexed_hash == Some(hash) {
+            return (self, FileRead::Unchanged);
+        }
+
+        let results = XmlExtractor::read_file(&self.path)
+            .filter(|source| !matches!(source, Ok(s) if s.root().kind == DocumentKind::Retention))
+            .map(|source| source.and_then(Bill::from_source))
+            .collect();
+
+        (self, FileRead::Parsed { hash, results })
+    }
+}
+
+/// Archivos xml y comprimidos de la carpeta, en el mismo orden que [`XmlExtractor::iter_xmls`].
+/// Los archivos o subcarpetas que no pueden leerse se devuelven como [`SkippedFile`].
+fn scan(root: &Path) -> Result<Vec<Result<DiskFile, SkippedFile>>, SatrError> {
+    XmlExtractor::check_root(root)?;
+    let mut files = Vec::new();
+
+    for entry in walkdir::WalkDir::new(root).sort_by_file_name() {
+        let file = match entry {
+            Ok(entry)
+                if entry.file_type().is_dir() || !XmlExtractor::is_supported_file(entry.path()) =>
+            {
+                continue;
+            }
+            Ok(entry) => DiskFile::new(root, &entry).map_err(|error| SkippedFile {
+                origin: Origin::File(entry.path().to_path_buf()),
+                error,
+            }),
+            Err(e) => Err(SkippedFile {
+                origin: Origin::File(e.path().unwrap_or(root).to_path_buf()),
+                error: e.into(),
+            }),
+        };
+
+        files.push(file);
+    }
+
+    Ok(files)
+}
+
+/// Hash del contenido de un archivo.
+fn hash_file(path: &Path) -> Result<u64, SatrError> {
+    let mut file = File::open(path).map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+    let mut buffer = [0; 64 * 1024];
+    let mut hash = Fnv::new();
+
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+        if read == 0 {
+            return Ok(hash.finish());
+        }
+
+        hash.update(&buffer[..read]);
+    }
+}
+
+/// Hash FNV-1a de 64 bits, estable entre versiones de Rust.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub(crate) fn new() -> Self {
+        Fnv(Self::OFFSET)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -4,3 +4,17 @@ macro_rules! parse_dec {
         f64::try_from($d).expect("Error al formatear decimal")
     };
 }
+
+/// Serializa los catálogos del SAT con su clave, la misma que aceptan en `TryFrom<String>`.
+#[macro_export]
+macro_rules! serialize_display {
+    ($($t: ty),+) => {
+        $(
+            impl serde::Serialize for $t {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+        )+
+    };
+}
@@ -2,10 +2,21 @@ use clap::Parser;
 
 mod bill;
 mod bill_extractor;
+mod cancellations;
+mod checker;
 mod cli;
+mod downloader;
+mod error;
+mod index;
 mod macros;
+mod metadata;
+mod parallel;
+mod reconciler;
+mod relations;
 mod reporter;
 mod reporter_factory;
+mod retention;
+mod retention_extractor;
 mod xml_extractor;
 
 #[cfg(test)]
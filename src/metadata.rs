@@ -0,0 +1,129 @@
+use std::{fs, path::Path, str::FromStr};
+
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+
+use crate::{bill::ReceiptType, error::SatrError};
+
+/// Columnas que deben tener los metadatos de la descarga masiva: Uuid, RfcEmisor,
+/// NombreEmisor, RfcReceptor, NombreReceptor, RfcPac, FechaEmision,
+/// FechaCertificacionSat, Monto, EfectoComprobante, Estatus y FechaCancelacion. Las
+/// columnas posteriores, como RfcACuentaTerceros y NombreACuentaTerceros, se ignoran.
+const COLUMNS: usize = 12;
+
+/// Metadatos de la descarga masiva del SAT: una línea por factura, con las columnas
+/// separadas por `~`. A diferencia de los xml, incluyen las facturas canceladas y el
+/// estatus actual de cada una.
+#[derive(Debug, Default)]
+pub struct Metadata {
+    pub records: Vec<MetadataRecord>,
+}
+
+impl Metadata {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, SatrError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+
+        Self::parse(&contents).map_err(|line| SatrError::Metadata {
+            path: path.to_path_buf(),
+            line,
+        })
+    }
+
+    /// En caso de error devuelve el número de la línea inválida. Se ignoran las líneas
+    /// vacías y la de encabezados.
+    pub fn parse(contents: &str) -> Result<Self, usize> {
+        let mut records = Vec::new();
+
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || MetadataRecord::is_header(line) {
+                continue;
+            }
+
+            records.push(MetadataRecord::from_line(line).ok_or(n + 1)?);
+        }
+
+        Ok(Metadata { records })
+    }
+}
+
+/// Estatus de una factura en los metadatos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataStatus {
+    /// 0
+    Cancelado,
+    /// 1
+    Vigente,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataRecord {
+    pub uuid: String,
+    pub issuer_rfc: String,
+    pub issuer_name: String,
+    pub recipient_rfc: String,
+    pub date: NaiveDateTime,
+
+    /// Total de la factura, en su moneda.
+    pub amount: Decimal,
+    pub receipt_type: ReceiptType,
+    pub status: MetadataStatus,
+    pub cancellation_date: Option<NaiveDateTime>,
+}
+
+impl MetadataRecord {
+    #[inline]
+    pub fn is_header(line: &str) -> bool {
+        line.split('~')
+            .next()
+            .is_some_and(|uuid| uuid.trim().eq_ignore_ascii_case("uuid"))
+    }
+
+    /// Lee una línea de los metadatos, o `None` si le faltan columnas o alguna es inválida.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('~').map(str::trim).collect();
+        if fields.len() < COLUMNS || !is_uuid(fields[0]) {
+            return None;
+        }
+
+        let status = match fields[10] {
+            "0" => MetadataStatus::Cancelado,
+            "1" => MetadataStatus::Vigente,
+            _ => return None,
+        };
+        let cancellation_date = match fields[11] {
+            "" => None,
+            date => Some(parse_date(date)?),
+        };
+
+        Some(MetadataRecord {
+            uuid: fields[0].to_uppercase(),
+            issuer_rfc: fields[1].to_string(),
+            issuer_name: fields[2].to_string(),
+            recipient_rfc: fields[3].to_string(),
+            date: parse_date(fields[6])?,
+            amount: Decimal::from_str(fields[8]).ok()?,
+            receipt_type: ReceiptType::try_from(fields[9].to_string()).ok()?,
+            status,
+            cancellation_date,
+        })
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.status == MetadataStatus::Cancelado
+    }
+}
+
+#[inline]
+pub fn is_uuid(s: &str) -> bool {
+    s.len() == 36 && s.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+fn parse_date(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+}
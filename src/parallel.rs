@@ -0,0 +1,95 @@
+use std::{collections::VecDeque, num::NonZeroUsize, thread};
+
+/// Elementos que cada hilo procesa por lote, limita la memoria usada a la vez.
+const BATCH_PER_JOB: usize = 64;
+
+/// Número de hilos a usar: el indicado, o uno por núcleo si es cero.
+pub fn jobs(requested: usize) -> usize {
+    if requested > 0 {
+        return requested;
+    }
+
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+/// Aplica una función a los elementos de un iterador en varios hilos, devolviendo los
+/// resultados en el mismo orden que los elementos. Los elementos se toman por lotes, por
+/// lo que el iterador original se sigue consumiendo de forma perezosa.
+pub struct OrderedMap<I, F, T> {
+    iter: I,
+    f: F,
+    jobs: usize,
+    results: VecDeque<T>,
+}
+
+impl<I, F, T> OrderedMap<I, F, T>
+where
+    I: Iterator,
+    I::Item: Send,
+    F: Fn(I::Item) -> T + Sync,
+    T: Send,
+{
+    pub fn new(iter: I, jobs: usize, f: F) -> Self {
+        OrderedMap {
+            iter,
+            f,
+            jobs: jobs.max(1),
+            results: VecDeque::new(),
+        }
+    }
+
+    fn fill(&mut self) {
+        let batch: Vec<I::Item> = self.iter.by_ref().take(self.jobs * BATCH_PER_JOB).collect();
+
+        if self.jobs == 1 || batch.len() <= 1 {
+            self.results.extend(batch.into_iter().map(&self.f));
+            return;
+        }
+
+        let chunk_size = batch.len().div_ceil(self.jobs);
+        let mut chunks = Vec::with_capacity(self.jobs);
+        let mut batch = batch.into_iter();
+        loop {
+            let chunk: Vec<I::Item> = batch.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+
+        let f = &self.f;
+        let mapped: Vec<Vec<T>> = thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .into_iter()
+                .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<T>>()))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Error en un hilo de procesamiento"))
+                .collect()
+        });
+
+        self.results.extend(mapped.into_iter().flatten());
+    }
+}
+
+impl<I, F, T> Iterator for OrderedMap<I, F, T>
+where
+    I: Iterator,
+    I::Item: Send,
+    F: Fn(I::Item) -> T + Sync,
+    T: Send,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.results.is_empty() {
+            self.fill();
+        }
+
+        self.results.pop_front()
+    }
+}
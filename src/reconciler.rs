@@ -0,0 +1,71 @@
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+use crate::{
+    bill::Bill,
+    bill_extractor::{self, BillExtractor, SkippedFile},
+    error::SatrError,
+    metadata::{Metadata, MetadataRecord},
+};
+
+pub struct Reconciler;
+
+impl Reconciler {
+    /// Compara los metadatos de la descarga masiva con las facturas de una carpeta. Las
+    /// facturas que no aparecen en los metadatos se ignoran, ya que los metadatos suelen
+    /// corresponder a un solo RFC y periodo. Las facturas se leen igual que en los
+    /// reportes, con el índice de la carpeta si existe.
+    pub fn reconcile<P: AsRef<Path>>(
+        metadata: &Metadata,
+        path: P,
+        jobs: usize,
+    ) -> Result<Reconciliation, SatrError> {
+        let config = bill_extractor::Config {
+            jobs,
+            ..Default::default()
+        };
+        let extraction = BillExtractor::new(Cow::Owned(config)).extract(path)?;
+        let mut reconciliation = Reconciliation {
+            skipped: extraction.skipped,
+            ..Default::default()
+        };
+        let mut bills: HashMap<String, Bill> = extraction
+            .bills
+            .into_iter()
+            .filter_map(|bill| Some((bill.uuid()?.to_uppercase(), bill)))
+            .collect();
+
+        for record in &metadata.records {
+            match bills.remove(&record.uuid) {
+                None => reconciliation.missing.push(record.clone()),
+                Some(bill) if record.is_cancelled() => {
+                    reconciliation.cancelled.push((record.clone(), bill))
+                }
+                Some(bill) if bill.total() != record.amount => {
+                    reconciliation.mismatched.push((record.clone(), bill))
+                }
+                Some(_) => reconciliation.matched += 1,
+            }
+        }
+
+        Ok(reconciliation)
+    }
+}
+
+/// Diferencias entre los metadatos y las facturas, en el orden de los metadatos.
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    /// Facturas de los metadatos que coinciden con su xml.
+    pub matched: usize,
+
+    /// Facturas de los metadatos sin xml.
+    pub missing: Vec<MetadataRecord>,
+
+    /// Facturas canceladas según los metadatos cuyo xml sí se tiene, por lo que se incluyen
+    /// en los reportes si no se excluyen con `--canceladas`.
+    pub cancelled: Vec<(MetadataRecord, Bill)>,
+
+    /// Facturas vigentes cuyo total no coincide con el monto de los metadatos.
+    pub mismatched: Vec<(MetadataRecord, Bill)>,
+
+    pub skipped: Vec<SkippedFile>,
+}
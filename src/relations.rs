@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use crate::bill::{Bill, ReceiptType, RelationType};
+
+/// Relaciones entre las facturas extraídas, indexadas por UUID.
+pub struct RelationGraph<'a> {
+    by_uuid: HashMap<String, &'a Bill>,
+
+    /// Comprobantes que relacionan a cada UUID.
+    incoming: HashMap<String, Vec<(RelationType, &'a Bill)>>,
+}
+
+impl<'a> RelationGraph<'a> {
+    pub fn new(bills: &'a [Bill]) -> Self {
+        let mut by_uuid = HashMap::new();
+        let mut incoming: HashMap<String, Vec<(RelationType, &Bill)>> = HashMap::new();
+
+        for bill in bills {
+            if let Some(uuid) = bill.uuid() {
+                by_uuid.insert(uuid.to_uppercase(), bill);
+            }
+
+            for (relation_type, uuid) in bill.related() {
+                incoming
+                    .entry(uuid.to_uppercase())
+                    .or_default()
+                    .push((relation_type, bill));
+            }
+        }
+
+        RelationGraph { by_uuid, incoming }
+    }
+
+    pub fn get(&self, uuid: &str) -> Option<&'a Bill> {
+        self.by_uuid.get(&uuid.to_uppercase()).copied()
+    }
+
+    /// Comprobantes relacionados por la factura, si no se encontraron se devuelve solo su UUID.
+    pub fn outgoing(&self, bill: &'a Bill) -> Vec<(RelationType, &'a str, Option<&'a Bill>)> {
+        bill.related()
+            .map(|(relation_type, uuid)| (relation_type, uuid, self.get(uuid)))
+            .collect()
+    }
+
+    /// Comprobantes que relacionan al UUID indicado.
+    pub fn incoming(&self, uuid: &str) -> &[(RelationType, &'a Bill)] {
+        self.incoming
+            .get(&uuid.to_uppercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Indica si otro comprobante sustituye a la factura.
+    pub fn is_substituted(&self, bill: &Bill) -> bool {
+        bill.uuid().is_some_and(|uuid| {
+            self.incoming(uuid)
+                .iter()
+                .any(|(relation_type, _)| *relation_type == RelationType::Sustitucion)
+        })
+    }
+
+    /// Factura original a la que se aplica una nota de crédito.
+    pub fn credit_note_original(&self, bill: &Bill) -> Option<&'a Bill> {
+        if bill.receipt_type != ReceiptType::Egreso {
+            return None;
+        }
+
+        bill.related()
+            .filter(|(relation_type, _)| *relation_type == RelationType::NotaCredito)
+            .find_map(|(_, uuid)| self.get(uuid))
+    }
+}
@@ -1,11 +1,18 @@
-use std::{borrow::Cow, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
 
 use rust_decimal::Decimal;
 
 use crate::{
-    bill::Bill,
-    bill_extractor::{self, BillExtractor},
-    cli::report::ReportType,
+    bill::{Bill, Concept, ReceiptType},
+    bill_extractor::{self, BillExtractor, DateConfig, Extraction, SkippedFile},
+    cli::report::{Breakdown, ReportType},
+    error::SatrError,
+    relations::RelationGraph,
+    retention::Retention,
 };
 
 pub struct Reporter {
@@ -17,31 +24,486 @@ impl Reporter {
         Reporter { config }
     }
 
+    fn metric(&self) -> fn(&Bill) -> Decimal {
+        match self.config.report_type {
+            ReportType::Total => Bill::total,
+            ReportType::Subtotal => Bill::subtotal,
+            ReportType::Descuento => Bill::discount,
+            ReportType::IvaTrasladado => Bill::iva_carried_forward,
+            ReportType::IvaRetenido => Bill::iva_withheld,
+            ReportType::IvaNeto => Bill::iva_net,
+            ReportType::IsrRetenido => Bill::isr_withheld,
+            ReportType::IsrNeto => Bill::isr_net,
+            ReportType::IepsTrasladado => Bill::ieps_carried_forward,
+            ReportType::IepsRetenido => Bill::ieps_withheld,
+            ReportType::IepsNeto => Bill::ieps_net,
+            ReportType::Nomina => Bill::gross_salary,
+            ReportType::ImpuestosLocales => Bill::local_taxes,
+            ReportType::ImpuestosLocalesTrasladados => Bill::local_taxes_carried_forward,
+            ReportType::ImpuestosLocalesRetenidos => Bill::local_taxes_withheld,
+        }
+    }
+
+    /// Signo de cada tipo de comprobante en el reporte: el de nómina solo acumula recibos de
+    /// nómina y el resto los excluye.
+    fn receipt_sign(&self) -> fn(&ReceiptType) -> Option<Decimal> {
+        match self.config.report_type {
+            ReportType::Nomina => ReceiptType::payroll_sign,
+            _ => ReceiptType::sign,
+        }
+    }
+
+    /// Acumula los montos por moneda aplicando el signo de cada tipo de comprobante,
+    /// la proporción indicada y su conversión a MXN.
     #[inline]
-    fn get_result<'a, F>(bills: &'a [Bill], f: F) -> Decimal
+    fn get_result<'a, I, F>(
+        entries: I,
+        f: F,
+        sign: fn(&ReceiptType) -> Option<Decimal>,
+    ) -> Result<Vec<CurrencyTotal>, SatrError>
     where
+        I: Iterator<Item = ReportEntry<'a>>,
         F: Fn(&'a Bill) -> Decimal,
     {
-        bills.iter().fold(Decimal::ZERO, |acc, bill| acc + f(bill))
+        let mut totals: BTreeMap<&str, CurrencyTotal> = BTreeMap::new();
+
+        for entry in entries {
+            let bill = entry.bill;
+            let Some(sign) = sign(&bill.receipt_type) else {
+                continue;
+            };
+
+            let amount = f(bill) * sign * entry.proportion;
+            let total = totals
+                .entry(bill.currency.as_str())
+                .or_insert_with(|| CurrencyTotal::new(bill.currency.clone()));
+            total.amount += amount;
+            total.mxn += amount * entry.exchange_rate()?;
+        }
+
+        Ok(totals.into_values().collect())
     }
 
-    pub fn money_report<P: AsRef<Path>>(&self, path: P) -> Result<Decimal, String> {
-        let config = &self.config.extractor_config;
-        let extractor = BillExtractor::new(Cow::Borrowed(config));
-        let bills = extractor.extract_bills(path)?;
-
-        let result = match self.config.report_type {
-            ReportType::Total => Self::get_result(&bills, Bill::total),
-            ReportType::Subtotal => Self::get_result(&bills, Bill::subtotal),
-            ReportType::Iva => Self::get_result(&bills, Bill::iva),
-            ReportType::Isr => Self::get_result(&bills, Bill::isr),
+    /// Acumula cantidades, importes e impuestos de los conceptos por clave de producto o
+    /// servicio, convertidos a MXN.
+    fn get_concepts<'a, I>(entries: I) -> Result<Vec<ConceptTotal>, SatrError>
+    where
+        I: Iterator<Item = ReportEntry<'a>>,
+    {
+        let mut totals: BTreeMap<&str, ConceptTotal> = BTreeMap::new();
+
+        for entry in entries {
+            let bill = entry.bill;
+            let Some(sign) = bill.receipt_type.sign() else {
+                continue;
+            };
+
+            let factor = sign * entry.proportion * entry.exchange_rate()?;
+            for concept in &bill.concepts.list {
+                let total = totals
+                    .entry(concept.key.as_str())
+                    .or_insert_with(|| ConceptTotal::new(concept));
+                total.quantity += concept.quantity * sign * entry.proportion;
+                total.amount += concept.net_value() * factor;
+                total.carried_forward += concept.taxes.carried_forward_total() * factor;
+                total.withheld += concept.taxes.withheld_total() * factor;
+            }
+        }
+
+        Ok(totals.into_values().collect())
+    }
+
+    /// Acumula los complementos de nómina por patrón y mes de pago. Un recibo con varios
+    /// complementos en el mismo mes se cuenta una sola vez.
+    fn get_payroll<'a, I>(entries: I) -> Vec<PayrollTotal>
+    where
+        I: Iterator<Item = ReportEntry<'a>>,
+    {
+        let mut totals: BTreeMap<(&str, String), PayrollTotal> = BTreeMap::new();
+
+        for entry in entries {
+            let bill = entry.bill;
+            let Some(sign) = bill.receipt_type.payroll_sign() else {
+                continue;
+            };
+
+            let factor = sign * entry.proportion;
+            let mut periods = HashSet::new();
+            for payroll in &bill.complement.payroll {
+                let period = payroll.pay_date.format("%Y-%m").to_string();
+                let total = totals
+                    .entry((bill.emisor.rfc.as_str(), period.clone()))
+                    .or_insert_with(|| PayrollTotal::new(bill, period.clone()));
+                if periods.insert(period) {
+                    total.receipts += 1;
+                }
+                total.paid_days += payroll.paid_days;
+                total.gross += payroll.gross() * factor;
+                total.taxed += payroll.taxed() * factor;
+                total.exempt += payroll.exempt() * factor;
+                total.isr_withheld += payroll.isr_withheld() * factor;
+                total.employment_subsidy += payroll.employment_subsidy() * factor;
+            }
+        }
+
+        totals.into_values().collect()
+    }
+
+    pub fn money_report<P: AsRef<Path>>(&self, path: P) -> Result<MoneyReport, SatrError> {
+        let config = self.extractor_config();
+        let extractor = BillExtractor::new(config);
+        let Extraction {
+            bills,
+            duplicates,
+            cancelled,
+            skipped,
+        } = extractor.extract(path)?;
+
+        let graph = RelationGraph::new(&bills);
+        let (entries, unmatched_payments) = if self.config.cash_basis {
+            let cash = CashBasisEntries::new(&bills, &self.config.extractor_config);
+
+            (cash.entries, cash.unmatched)
+        } else {
+            (self.accrual_entries(&bills, &graph), Vec::new())
+        };
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|e| !self.config.exclude_substituted || !graph.is_substituted(e.bill))
+            .collect();
+
+        let by_currency =
+            Self::get_result(entries.iter().copied(), self.metric(), self.receipt_sign())?;
+        let by_concept = match self.config.breakdown {
+            Some(Breakdown::Concepto) => Self::get_concepts(entries.iter().copied())?,
+            None => Vec::new(),
         };
+        let by_payroll = match self.config.report_type {
+            ReportType::Nomina => Self::get_payroll(entries.into_iter()),
+            _ => Vec::new(),
+        };
+        let result = by_currency.iter().map(|t| t.mxn).sum();
+
+        Ok(MoneyReport {
+            result,
+            by_currency,
+            by_concept,
+            by_payroll,
+            duplicates,
+            cancelled,
+            skipped,
+            unmatched_payments,
+        })
+    }
+
+    /// Facturas acumulables por su fecha de emisión. Si se netean las notas de crédito, estas
+    /// se acumulan en la fecha de la factura que modifican.
+    fn accrual_entries<'a>(
+        &self,
+        bills: &'a [Bill],
+        graph: &RelationGraph<'a>,
+    ) -> Vec<ReportEntry<'a>> {
+        let config = &self.config.extractor_config;
+
+        bills
+            .iter()
+            .filter(|b| {
+                let date = match graph.credit_note_original(b) {
+                    Some(original) if self.config.net_credit_notes => original.date,
+                    _ => b.date,
+                };
+
+                config.dates.contains(date) && config.receipt_type_match(b)
+            })
+            .map(ReportEntry::new)
+            .collect()
+    }
+
+    /// Los pagos, las sustituciones y las notas de crédito pueden relacionar facturas de
+    /// otros periodos o tipos, por lo que en esos casos las fechas y tipos se filtran al
+    /// momento del reporte.
+    fn extractor_config(&self) -> Cow<'_, bill_extractor::Config> {
+        let config = &self.config.extractor_config;
+        if !self.config.cash_basis
+            && !self.config.exclude_substituted
+            && !self.config.net_credit_notes
+        {
+            return Cow::Borrowed(config);
+        }
+
+        let mut config = config.clone();
+        config.dates = DateConfig::from((None, None));
+        config.receipt_types.clear();
 
-        Ok(result)
+        Cow::Owned(config)
+    }
+}
+
+/// Factura acumulable en un reporte, con la proporción de su monto que corresponde al
+/// periodo.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportEntry<'a> {
+    pub bill: &'a Bill,
+    pub proportion: Decimal,
+
+    /// Tipo de cambio a MXN en la fecha del pago, solo para las facturas PPD en flujo de
+    /// efectivo. Si no se indica se usa el de la factura.
+    pub exchange_rate: Option<Decimal>,
+}
+
+impl<'a> ReportEntry<'a> {
+    /// La factura completa, con su propio tipo de cambio.
+    #[inline]
+    pub fn new(bill: &'a Bill) -> Self {
+        ReportEntry {
+            bill,
+            proportion: Decimal::ONE,
+            exchange_rate: None,
+        }
+    }
+
+    #[inline]
+    pub fn exchange_rate(&self) -> Result<Decimal, SatrError> {
+        match self.exchange_rate {
+            Some(rate) => Ok(rate),
+            None => self.bill.exchange_rate(),
+        }
+    }
+}
+
+/// Facturas acumulables en flujo de efectivo: las facturas PUE se acumulan completas en su
+/// fecha de emisión y las PPD en la proporción pagada en cada fecha de pago, convertida a
+/// MXN con el tipo de cambio del pago.
+pub struct CashBasisEntries<'a> {
+    pub entries: Vec<ReportEntry<'a>>,
+
+    /// UUID de las facturas pagadas dentro del periodo que no se encontraron.
+    pub unmatched: Vec<String>,
+}
+
+impl<'a> CashBasisEntries<'a> {
+    pub fn new(bills: &'a [Bill], config: &bill_extractor::Config) -> Self {
+        let in_period = |date| config.dates.contains(date);
+        let type_match = |bill: &Bill| config.receipt_type_match(bill);
+
+        let deferred: HashMap<String, &Bill> = bills
+            .iter()
+            .filter(|b| b.is_deferred())
+            .filter_map(|b| b.uuid().map(|uuid| (uuid.to_uppercase(), b)))
+            .collect();
+
+        let mut entries: Vec<ReportEntry> = bills
+            .iter()
+            .filter(|b| !b.is_deferred() && in_period(b.date) && type_match(b))
+            .map(ReportEntry::new)
+            .collect();
+        let mut unmatched = Vec::new();
+
+        let payments = bills
+            .iter()
+            .filter_map(|b| b.complement.payments.as_ref())
+            .flat_map(|p| p.related_documents())
+            .filter(|(payment, _)| in_period(payment.date));
+
+        for (payment, document) in payments {
+            match deferred.get(&document.uuid.to_uppercase()) {
+                Some(bill) if bill.total.is_zero() || !type_match(bill) => {}
+                Some(bill) => entries.push(ReportEntry {
+                    bill,
+                    proportion: document.paid() / bill.total,
+                    exchange_rate: payment.exchange_rate_for(document),
+                }),
+                None => unmatched.push(document.uuid.clone()),
+            }
+        }
+
+        CashBasisEntries { entries, unmatched }
+    }
+}
+
+pub struct MoneyReport {
+    /// Resultado en MXN.
+    pub result: Decimal,
+
+    pub by_currency: Vec<CurrencyTotal>,
+
+    /// Solo con el desglose por concepto.
+    pub by_concept: Vec<ConceptTotal>,
+
+    /// Solo en el reporte de nómina.
+    pub by_payroll: Vec<PayrollTotal>,
+
+    /// Facturas descartadas por tener un UUID repetido.
+    pub duplicates: Vec<Bill>,
+
+    /// Facturas excluidas por estar en la lista de canceladas.
+    pub cancelled: Vec<Bill>,
+
+    /// Archivos que no pudieron leerse como CFDI.
+    pub skipped: Vec<SkippedFile>,
+
+    /// UUID de facturas PPD con pagos en el periodo que no se encontraron, solo en flujo de
+    /// efectivo.
+    pub unmatched_payments: Vec<String>,
+}
+
+pub struct CurrencyTotal {
+    pub currency: String,
+
+    /// Monto en la moneda original.
+    pub amount: Decimal,
+
+    /// Monto convertido a MXN con el tipo de cambio de cada factura.
+    pub mxn: Decimal,
+}
+
+impl CurrencyTotal {
+    fn new(currency: String) -> Self {
+        CurrencyTotal {
+            currency,
+            amount: Decimal::ZERO,
+            mxn: Decimal::ZERO,
+        }
+    }
+}
+
+/// Totales de los conceptos con la misma clave de producto o servicio, en MXN.
+pub struct ConceptTotal {
+    pub key: String,
+
+    /// Descripción del primer concepto encontrado con la clave.
+    pub description: String,
+
+    pub quantity: Decimal,
+
+    /// Importe menos descuento.
+    pub amount: Decimal,
+    pub carried_forward: Decimal,
+    pub withheld: Decimal,
+}
+
+impl ConceptTotal {
+    fn new(concept: &Concept) -> Self {
+        ConceptTotal {
+            key: concept.key.clone(),
+            description: concept.description.clone(),
+            quantity: Decimal::ZERO,
+            amount: Decimal::ZERO,
+            carried_forward: Decimal::ZERO,
+            withheld: Decimal::ZERO,
+        }
+    }
+}
+
+/// Totales de los recibos de nómina de un patrón en un mes, según su fecha de pago.
+pub struct PayrollTotal {
+    pub employer_rfc: String,
+    pub employer_name: Option<String>,
+
+    /// Mes de pago en formato YYYY-MM.
+    pub period: String,
+
+    pub receipts: usize,
+    pub paid_days: Decimal,
+
+    /// Percepciones gravadas más exentas.
+    pub gross: Decimal,
+    pub taxed: Decimal,
+    pub exempt: Decimal,
+    pub isr_withheld: Decimal,
+    pub employment_subsidy: Decimal,
+}
+
+impl PayrollTotal {
+    fn new(bill: &Bill, period: String) -> Self {
+        PayrollTotal {
+            employer_rfc: bill.emisor.rfc.clone(),
+            employer_name: bill.emisor.name.clone(),
+            period,
+            receipts: 0,
+            paid_days: Decimal::ZERO,
+            gross: Decimal::ZERO,
+            taxed: Decimal::ZERO,
+            exempt: Decimal::ZERO,
+            isr_withheld: Decimal::ZERO,
+            employment_subsidy: Decimal::ZERO,
+        }
+    }
+}
+
+/// Totales de las constancias de retenciones emitidas por un mismo retenedor.
+pub struct RetentionTotal {
+    pub retainer_rfc: String,
+    pub retainer_name: Option<String>,
+    pub documents: usize,
+
+    /// Monto total de las operaciones.
+    pub operation: Decimal,
+    pub taxed: Decimal,
+    pub exempt: Decimal,
+    pub isr_withheld: Decimal,
+    pub iva_withheld: Decimal,
+
+    /// IVA cobrado a los clientes, solo de constancias de plataformas tecnológicas.
+    pub iva_carried_forward: Decimal,
+
+    /// Comisiones cobradas por las plataformas tecnológicas.
+    pub platform_fees: Decimal,
+}
+
+impl RetentionTotal {
+    fn new(retention: &Retention) -> Self {
+        RetentionTotal {
+            retainer_rfc: retention.retainer.rfc.clone(),
+            retainer_name: retention.retainer.name.clone(),
+            documents: 0,
+            operation: Decimal::ZERO,
+            taxed: Decimal::ZERO,
+            exempt: Decimal::ZERO,
+            isr_withheld: Decimal::ZERO,
+            iva_withheld: Decimal::ZERO,
+            iva_carried_forward: Decimal::ZERO,
+            platform_fees: Decimal::ZERO,
+        }
+    }
+
+    /// Acumula las constancias por RFC del retenedor.
+    pub fn by_retainer(retentions: &[Retention]) -> Vec<RetentionTotal> {
+        let mut totals: BTreeMap<&str, RetentionTotal> = BTreeMap::new();
+
+        for retention in retentions {
+            let total = totals
+                .entry(retention.retainer.rfc.as_str())
+                .or_insert_with(|| RetentionTotal::new(retention));
+            total.documents += 1;
+            total.operation += retention.totals.operation;
+            total.taxed += retention.totals.taxed;
+            total.exempt += retention.totals.exempt;
+            total.isr_withheld += retention.isr_withheld();
+            total.iva_withheld += retention.iva_withheld();
+            if let Some(platforms) = &retention.complement.platforms {
+                total.iva_carried_forward += platforms.iva_carried_forward;
+                total.platform_fees += platforms.platform_fees;
+            }
+        }
+
+        totals.into_values().collect()
     }
 }
 
 pub struct Config {
     pub extractor_config: bill_extractor::Config,
     pub report_type: ReportType,
+
+    /// Acumula las facturas en las fechas en que fueron pagadas.
+    pub cash_basis: bool,
+
+    /// Excluye las facturas sustituidas por otro comprobante.
+    pub exclude_substituted: bool,
+
+    /// Acumula las notas de crédito en la fecha de la factura que modifican.
+    pub net_credit_notes: bool,
+
+    /// Desglose adicional al resultado.
+    pub breakdown: Option<Breakdown>,
 }
@@ -1,13 +1,15 @@
 use crate::{
+    bill::ReceiptType,
     bill_extractor::{self, DateConfig},
     cli::report::{ReportType, Subject, SubjectType},
+    error::SatrError,
     reporter::{Config, Reporter},
 };
 
 pub struct ReporterFactory;
 
 impl ReporterFactory {
-    pub fn from_subject(subject: Subject) -> Reporter {
+    pub fn from_subject(subject: Subject) -> Result<Reporter, SatrError> {
         let config = match subject {
             Subject::Emisor {
                 emisor_rfc,
@@ -16,10 +18,18 @@ impl ReporterFactory {
                 let args = report_type.args();
                 Config {
                     report_type: ReportType::from(&report_type),
+                    cash_basis: args.cash_basis,
+                    exclude_substituted: args.exclude_substituted,
+                    net_credit_notes: args.net_credit_notes,
+                    breakdown: args.by,
                     extractor_config: bill_extractor::Config {
-                        subject_rfc: emisor_rfc,
+                        subject_rfc: Some(emisor_rfc),
                         subject_type: SubjectType::Emisor,
                         dates: DateConfig::from((args.date_start, args.date_end)),
+                        deduplicate: args.dedup.deduplicate(),
+                        receipt_types: args.receipt_types.clone(),
+                        cancellations: args.cancellations()?,
+                        jobs: args.jobs,
                     },
                 }
             }
@@ -30,15 +40,36 @@ impl ReporterFactory {
                 let args = report_type.args();
                 Config {
                     report_type: ReportType::from(&report_type),
+                    cash_basis: args.cash_basis,
+                    exclude_substituted: args.exclude_substituted,
+                    net_credit_notes: args.net_credit_notes,
+                    breakdown: args.by,
                     extractor_config: bill_extractor::Config {
-                        subject_rfc: receptor_rfc,
+                        subject_rfc: Some(receptor_rfc),
                         subject_type: SubjectType::Receptor,
                         dates: DateConfig::from((args.date_start, args.date_end)),
+                        deduplicate: args.dedup.deduplicate(),
+                        receipt_types: args.receipt_types.clone(),
+                        cancellations: args.cancellations()?,
+                        jobs: args.jobs,
                     },
                 }
             }
         };
 
-        Reporter::new(config)
+        // Los complementos de pago no acumulan montos propios, en flujo de efectivo se
+        // reflejan en las facturas PPD que pagan.
+        if config.cash_basis
+            && config
+                .extractor_config
+                .receipt_types
+                .contains(&ReceiptType::Pago)
+        {
+            return Err(SatrError::InvalidOptions(
+                "--tipo pago no puede usarse con --flujo, los pagos se acumulan en las facturas PPD que pagan".to_string(),
+            ));
+        }
+
+        Ok(Reporter::new(config))
     }
 }
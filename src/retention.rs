@@ -0,0 +1,174 @@
+use chrono::{DateTime, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    bill::complement::DigitalStamp,
+    bill_extractor::SkippedFile,
+    error::SatrError,
+    xml_extractor::{Origin, XmlSource},
+};
+use platforms::PlatformServices;
+
+pub mod platforms;
+
+/// CFDI de Retenciones e Información de Pagos (`retenciones:Retenciones`), versiones 1.0 y
+/// 2.0. Los atributos de la versión 1.0 se leen con sus nombres originales como alias.
+#[derive(Debug, Deserialize)]
+pub struct Retention {
+    /// La versión 1.0 incluye la zona horaria.
+    #[serde(rename = "@FechaExp", deserialize_with = "deserialize_date")]
+    pub date: NaiveDateTime,
+
+    #[serde(rename = "Emisor")]
+    pub retainer: Retainer,
+
+    #[serde(rename = "Receptor")]
+    pub recipient: RetentionRecipient,
+
+    #[serde(rename = "Periodo")]
+    pub period: Period,
+
+    #[serde(rename = "Totales")]
+    pub totals: RetentionTotals,
+
+    #[serde(rename = "Complemento", default)]
+    pub complement: RetentionComplement,
+
+    /// Archivo del que se extrajo la constancia.
+    #[serde(skip)]
+    pub source: Option<Origin>,
+}
+
+impl Retention {
+    pub fn from_xml(xml: &str) -> Result<Self, SatrError> {
+        Ok(quick_xml::de::from_str(xml)?)
+    }
+
+    /// Deserializa una constancia conservando el archivo del que proviene.
+    pub fn from_source(source: XmlSource) -> Result<Self, SkippedFile> {
+        let XmlSource { origin, contents } = source;
+
+        match Self::from_xml(&contents) {
+            Ok(mut retention) => {
+                retention.source = Some(origin);
+                Ok(retention)
+            }
+            Err(error) => Err(SkippedFile { origin, error }),
+        }
+    }
+
+    /// Folio fiscal asignado por el timbre fiscal digital.
+    #[inline]
+    pub fn uuid(&self) -> Option<&str> {
+        self.complement.stamp.as_ref().map(|s| s.uuid.as_str())
+    }
+
+    /// RFC del receptor, solo si es nacional.
+    #[inline]
+    pub fn recipient_rfc(&self) -> Option<&str> {
+        self.recipient.national.as_ref().map(|n| n.rfc.as_str())
+    }
+
+    pub fn isr_withheld(&self) -> Decimal {
+        self.withheld(WithheldTax::is_isr)
+    }
+
+    pub fn iva_withheld(&self) -> Decimal {
+        self.withheld(WithheldTax::is_iva)
+    }
+
+    fn withheld(&self, f: fn(&WithheldTax) -> bool) -> Decimal {
+        self.totals
+            .withheld
+            .iter()
+            .filter(|t| f(t))
+            .map(|t| t.value)
+            .sum()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Retainer {
+    #[serde(rename = "@RfcE", alias = "@RFCEmisor")]
+    pub rfc: String,
+
+    #[serde(rename = "@NomDenRazSocE")]
+    pub name: Option<String>,
+}
+
+/// Los receptores extranjeros no tienen RFC, solo se lee el nodo nacional.
+#[derive(Debug, Deserialize)]
+pub struct RetentionRecipient {
+    #[serde(rename = "Nacional")]
+    pub national: Option<NationalRecipient>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NationalRecipient {
+    #[serde(rename = "@RfcR", alias = "@RFCRecep")]
+    pub rfc: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Period {
+    #[serde(rename = "@Ejercicio", alias = "@Ejerc")]
+    pub fiscal_year: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionTotals {
+    #[serde(rename = "@MontoTotOperacion", alias = "@montoTotOperacion")]
+    pub operation: Decimal,
+
+    #[serde(rename = "@MontoTotGrav", alias = "@montoTotGrav")]
+    pub taxed: Decimal,
+
+    #[serde(rename = "@MontoTotExent", alias = "@montoTotExent")]
+    pub exempt: Decimal,
+
+    #[serde(rename = "ImpRetenidos", default)]
+    pub withheld: Vec<WithheldTax>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WithheldTax {
+    /// 001 ISR, 002 IVA, 003 IEPS; en la versión 1.0 01, 02 y 03.
+    #[serde(rename = "@ImpuestoRet", alias = "@Impuesto")]
+    pub ty: Option<String>,
+
+    #[serde(rename = "@MontoRet", alias = "@montoRet")]
+    pub value: Decimal,
+}
+
+impl WithheldTax {
+    fn is_isr(&self) -> bool {
+        matches!(self.ty.as_deref(), Some("001" | "01"))
+    }
+
+    fn is_iva(&self) -> bool {
+        matches!(self.ty.as_deref(), Some("002" | "02"))
+    }
+}
+
+/// Nodo `retenciones:Complemento`.
+#[derive(Debug, Deserialize, Default)]
+pub struct RetentionComplement {
+    #[serde(rename = "TimbreFiscalDigital")]
+    pub stamp: Option<DigitalStamp>,
+
+    #[serde(rename = "ServiciosPlataformasTecnologicas")]
+    pub platforms: Option<PlatformServices>,
+}
+
+fn deserialize_date<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+
+    DateTime::parse_from_rfc3339(&s)
+        .map(|d| d.naive_local())
+        .or_else(|_| s.parse::<NaiveDateTime>())
+        .map_err(serde::de::Error::custom)
+}
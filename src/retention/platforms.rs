@@ -0,0 +1,17 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Complemento `plataformasTecnologicas:ServiciosPlataformasTecnologicas`, emitido por las
+/// plataformas digitales a las personas que enajenan bienes o prestan servicios a través
+/// de ellas. Solo se leen los totales que se acumulan en el reporte de retenciones, el
+/// detalle por servicio se omite.
+#[derive(Debug, Deserialize)]
+pub struct PlatformServices {
+    /// IVA cobrado a los clientes por los servicios.
+    #[serde(rename = "@TotalIVATrasladado")]
+    pub iva_carried_forward: Decimal,
+
+    /// Comisiones cobradas por la plataforma.
+    #[serde(rename = "@MonTotalporUsoPlataforma")]
+    pub platform_fees: Decimal,
+}
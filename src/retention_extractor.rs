@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::{
+    bill_extractor::{Deduplicator, SkippedFile},
+    error::SatrError,
+    retention::Retention,
+    xml_extractor::{DocumentKind, XmlExtractor},
+};
+
+/// Extrae las constancias de retenciones emitidas a un RFC.
+pub struct RetentionExtractor {
+    config: Config,
+}
+
+impl RetentionExtractor {
+    pub fn new(config: Config) -> Self {
+        RetentionExtractor { config }
+    }
+
+    #[inline]
+    fn filter(&self, retention: &Retention) -> bool {
+        retention.recipient_rfc() == Some(self.config.recipient_rfc.as_str())
+            && self
+                .config
+                .fiscal_year
+                .is_none_or(|year| retention.period.fiscal_year == year)
+    }
+
+    /// Extrae las constancias separando las descartadas por tener un UUID repetido y los
+    /// archivos que no pudieron leerse. Los CFDI de ingreso, egreso, etc. se ignoran. Solo
+    /// falla si no puede leerse la carpeta.
+    pub fn extract<P: AsRef<Path>>(&self, path: P) -> Result<RetentionExtraction, SatrError> {
+        let mut deduplicator = Deduplicator::new(self.config.deduplicate);
+        let mut extraction = RetentionExtraction::default();
+
+        for source in XmlExtractor::iter_xmls(path)? {
+            let source = match source {
+                Ok(source) if source.root().kind == DocumentKind::Retention => source,
+                Ok(_) => continue,
+                Err(skipped) => {
+                    extraction.skipped.push(skipped);
+                    continue;
+                }
+            };
+
+            match Retention::from_source(source) {
+                Ok(retention) if !self.filter(&retention) => {}
+                Ok(retention) if !deduplicator.is_unique(retention.uuid()) => {
+                    extraction.duplicates.push(retention)
+                }
+                Ok(retention) => extraction.retentions.push(retention),
+                Err(skipped) => extraction.skipped.push(skipped),
+            }
+        }
+
+        Ok(extraction)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RetentionExtraction {
+    pub retentions: Vec<Retention>,
+    pub duplicates: Vec<Retention>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// RFC del receptor de las constancias, a quien se le retuvieron los impuestos.
+    pub recipient_rfc: String,
+
+    /// Ejercicio del periodo de las constancias, si no se indica se incluyen todos.
+    pub fiscal_year: Option<i32>,
+
+    pub deduplicate: bool,
+}
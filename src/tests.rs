@@ -1,9 +1,43 @@
-use std::path::PathBuf;
+use std::{
+    borrow::Cow,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::Parser;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{bill::Bill, xml_extractor::XmlExtractor};
+use crate::{
+    bill::{Bill, CfdiVersion, FactorType, ReceiptType, RelationType, Tax, TaxType},
+    bill_extractor::{self, BillExtractor, DateConfig, SkippedFile},
+    cancellations::CancellationList,
+    checker::{CheckStatus, Checker},
+    cli::{
+        report::{ReportTypeSubCommand, SubjectType},
+        Action, SatrCommand,
+    },
+    downloader::{self, fiel::Fiel, Downloader, Endpoints, RequestType},
+    error::SatrError,
+    index::{Fnv, Index, INDEX_VERSION},
+    metadata::{Metadata, MetadataStatus},
+    parallel::OrderedMap,
+    reconciler::Reconciler,
+    relations::RelationGraph,
+    reporter::{CashBasisEntries, RetentionTotal},
+    reporter_factory::ReporterFactory,
+    retention::Retention,
+    retention_extractor::{self, RetentionExtractor},
+    xml_extractor::{
+        archives::{ArchiveFormat, Tar},
+        Origin, XmlExtractor, XmlSource,
+    },
+};
 
 #[inline]
 fn assets_path() -> std::path::PathBuf {
@@ -24,15 +58,84 @@ fn deserialize_works() {
     println!("{:#?}", bill);
 }
 
+#[test]
+fn stamp_deserialize_works() {
+    let mut file_path = assets_path();
+    file_path.push("factura.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let bill = Bill::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.uuid(), Some("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"));
+    let stamp = bill.complement.stamp.expect("Timbre fiscal no encontrado");
+    assert_eq!(stamp.provider_rfc, "SPR190613I52");
+}
+
+#[test]
+fn withheld_and_carried_forward_taxes_are_separated() {
+    let mut file_path = assets_path();
+    file_path.push("factura.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let bill = Bill::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.iva_carried_forward(), Decimal::new(160000, 2));
+    assert_eq!(bill.iva_withheld(), Decimal::new(106667, 2));
+    assert_eq!(bill.iva_net(), Decimal::new(53333, 2));
+    assert_eq!(bill.isr_withheld(), Decimal::new(100000, 2));
+}
+
+#[test]
+fn deserialize_v33_works() {
+    let mut file_path = assets_path();
+    file_path.push("factura_33.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let bill = Bill::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.version, CfdiVersion::V33);
+    assert!(bill.exportation.is_none());
+    assert!(bill.recipient.zip_code.is_none());
+}
+
+#[test]
+fn v40_requires_version_attributes() {
+    let mut file_path = assets_path();
+    file_path.push("factura.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let file = file.replace(r#" Exportacion="01""#, "");
+
+    assert!(Bill::from_xml(&file).is_err());
+}
+
 #[test]
 fn zip_extract_works() {
     let mut file_path = assets_path();
     file_path.push("factura.zip");
 
-    let files_content =
-        XmlExtractor::extract_from_zip(file_path).expect("Error al extraer los  archivos XML");
+    let files_content: Vec<_> = XmlExtractor::read_file(file_path)
+        .collect::<Result<_, _>>()
+        .expect("Error al extraer los  archivos XML");
 
-    assert!(files_content.len() > 0);
+    assert!(!files_content.is_empty());
+}
+
+#[test]
+fn zip_entries_keep_their_origin() {
+    let file_path = assets_path().join("factura.zip");
+
+    let sources: Vec<_> = XmlExtractor::read_file(&file_path)
+        .collect::<Result<_, _>>()
+        .expect("Error al extraer los  archivos XML");
+
+    assert_eq!(
+        sources[0].origin,
+        Origin::Archive {
+            path: file_path,
+            entry: "6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D.xml".to_string()
+        }
+    );
 }
 
 #[derive(Deserialize)]
@@ -57,3 +160,1461 @@ fn date_time_parse_works() {
 
     println!("{:?}", datetime);
 }
+
+/// Carpeta temporal propia de cada prueba, se borra al terminar aunque la prueba falle.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "satr-{name}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).expect("Error al crear el directorio temporal");
+
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+fn duplicated_zips_dir(name: &str) -> TempDir {
+    let dir = TempDir::new(name);
+
+    let mut zip_path = assets_path();
+    zip_path.push("factura.zip");
+    for copy in ["a.zip", "b.zip"] {
+        std::fs::copy(&zip_path, dir.join(copy)).expect("Error al copiar el zip");
+    }
+
+    dir
+}
+
+#[test]
+fn extract_discards_duplicated_uuids() {
+    let dir = duplicated_zips_dir("dedup");
+    let config = bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor);
+
+    let extraction = BillExtractor::new(Cow::Owned(config))
+        .extract(&dir)
+        .expect("Error al extraer las facturas");
+
+    assert_eq!(extraction.bills.len(), 1);
+    assert_eq!(extraction.duplicates.len(), 1);
+}
+
+#[test]
+fn extract_keeps_duplicates_when_disabled() {
+    let dir = duplicated_zips_dir("no-dedup");
+    let config = bill_extractor::Config {
+        deduplicate: false,
+        ..bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor)
+    };
+
+    let extraction = BillExtractor::new(Cow::Owned(config))
+        .extract(&dir)
+        .expect("Error al extraer las facturas");
+
+    assert_eq!(extraction.bills.len(), 2);
+    assert!(extraction.duplicates.is_empty());
+}
+
+#[test]
+fn unreadable_files_are_skipped() {
+    let dir = TempDir::new("unreadable");
+    std::fs::copy(assets_path().join("factura.zip"), dir.join("a.zip"))
+        .expect("Error al copiar el zip");
+    std::fs::write(dir.join("b.zip"), b"no es un zip").expect("Error al escribir el zip");
+    std::fs::write(
+        dir.join("c.xml"),
+        b"<cfdi:Comprobante Descripcion=\"\xf1\"/>",
+    )
+    .expect("Error al escribir el xml");
+
+    let config = bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor);
+    let extractor = BillExtractor::new(Cow::Owned(config));
+    let skipped = |extraction: &bill_extractor::Extraction| {
+        extraction
+            .skipped
+            .iter()
+            .map(|s| s.origin.clone())
+            .collect::<Vec<_>>()
+    };
+    let expected = [
+        Origin::File(dir.join("b.zip")),
+        Origin::File(dir.join("c.xml")),
+    ];
+
+    let extraction = extractor
+        .extract(&dir)
+        .expect("Error al extraer las facturas");
+    assert_eq!(extraction.bills.len(), 1);
+    assert_eq!(skipped(&extraction), expected);
+
+    Index::default()
+        .save(&dir)
+        .expect("Error al crear el índice");
+    let extraction = extractor
+        .extract(&dir)
+        .expect("Error al extraer las facturas del índice");
+    assert_eq!(extraction.bills.len(), 1);
+    assert_eq!(skipped(&extraction), expected);
+
+    // Solo una carpeta que no puede leerse detiene la extracción.
+    assert!(extractor.extract(dir.join("no-existe")).is_err());
+}
+
+#[test]
+fn ieps_and_exempt_taxes_work() {
+    let mut file_path = assets_path();
+    file_path.push("factura_ieps.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let bill = Bill::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.ieps_carried_forward(), Decimal::new(1842, 2));
+    assert_eq!(bill.ieps_withheld(), Decimal::ZERO);
+    assert_eq!(bill.ieps_net(), Decimal::new(1842, 2));
+    assert_eq!(bill.iva_net(), Decimal::new(15856, 2));
+
+    let carried_forward = bill
+        .taxes
+        .carried_forward
+        .expect("Traslados no encontrados");
+    let exempt = carried_forward
+        .taxes
+        .iter()
+        .find(|tax| tax.factor_type == Some(FactorType::Exento))
+        .expect("Traslado exento no encontrado");
+    assert_eq!(exempt.base, Some(Decimal::new(8000, 2)));
+    assert_eq!(exempt.value, None);
+    assert_eq!(
+        carried_forward.taxes[1].factor_type,
+        Some(FactorType::Cuota)
+    );
+}
+
+#[test]
+fn credit_notes_are_subtracted() {
+    let mut file_path = assets_path();
+    file_path.push("nota_credito.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let bill = Bill::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.receipt_type, ReceiptType::Egreso);
+    assert_eq!(bill.receipt_type.sign(), Some(Decimal::NEGATIVE_ONE));
+    assert_eq!(ReceiptType::Pago.sign(), None);
+}
+
+#[test]
+fn foreign_currency_requires_exchange_rate() {
+    let mut file_path = assets_path();
+    file_path.push("factura.xml");
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let usd = file.replace(r#"Moneda="MXN""#, r#"Moneda="USD""#);
+    let bill = Bill::from_xml(&usd).expect("Error al deserializar el XML");
+    assert!(bill.exchange_rate().is_err());
+
+    let usd = file.replace(r#"Moneda="MXN""#, r#"Moneda="USD" TipoCambio="17.5""#);
+    let bill = Bill::from_xml(&usd).expect("Error al deserializar el XML");
+    assert_eq!(bill.exchange_rate().ok(), Some(Decimal::new(175, 1)));
+}
+
+fn read_bill(name: &str) -> Bill {
+    let mut file_path = assets_path();
+    file_path.push(name);
+
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    Bill::from_xml(&file).expect("Error al deserializar el XML")
+}
+
+#[test]
+fn payments_deserialize_works() {
+    let bill = read_bill("pago.xml");
+
+    assert_eq!(bill.receipt_type, ReceiptType::Pago);
+    let payments = bill
+        .complement
+        .payments
+        .expect("Complemento de pagos no encontrado");
+    assert_eq!(payments.list.len(), 2);
+
+    let document = &payments.list[1].related_documents[0];
+    assert_eq!(document.installment, Some(2));
+    assert_eq!(document.paid(), Decimal::new(580000, 2));
+    assert_eq!(document.outstanding_balance, Some(Decimal::ZERO));
+
+    let taxes = payments.list[0]
+        .taxes
+        .as_ref()
+        .expect("Impuestos no encontrados");
+    let carried_forward = taxes
+        .carried_forward
+        .as_ref()
+        .expect("Traslados no encontrados");
+    assert_eq!(
+        Tax::sum_iter(carried_forward.taxes.iter(), TaxType::Iva),
+        Decimal::new(80000, 2)
+    );
+}
+
+#[test]
+fn v10_payments_use_the_document_exchange_rate() {
+    let mut invoice = read_bill("factura_ppd.xml");
+    invoice.currency = "USD".to_string();
+    invoice.exchange_rate = Some(Decimal::new(1700, 2));
+
+    let payment = read_bill("pago_10.xml");
+    assert_eq!(payment.version, CfdiVersion::V33);
+    let payments = payment
+        .complement
+        .payments
+        .as_ref()
+        .expect("Complemento de pagos no encontrado");
+    let (p, document) = payments
+        .related_documents()
+        .next()
+        .expect("Documento relacionado no encontrado");
+    assert_eq!(document.equivalence, Some(Decimal::new(5, 2)));
+    assert_eq!(p.exchange_rate_for(document), Some(Decimal::new(20, 0)));
+
+    let bills = vec![invoice, payment];
+    let config = bill_extractor::Config {
+        dates: DateConfig::from((
+            NaiveDate::from_ymd_opt(2024, 2, 1),
+            NaiveDate::from_ymd_opt(2024, 2, 29),
+        )),
+        ..bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor)
+    };
+
+    let cash = CashBasisEntries::new(&bills, &config);
+
+    assert_eq!(cash.entries.len(), 1);
+    assert_eq!(
+        cash.entries[0].exchange_rate().ok(),
+        Some(Decimal::new(20, 0))
+    );
+}
+
+#[test]
+fn cash_basis_counts_deferred_bills_when_paid() {
+    let bills = vec![read_bill("factura_ppd.xml"), read_bill("pago.xml")];
+    let config = bill_extractor::Config {
+        dates: DateConfig::from((
+            NaiveDate::from_ymd_opt(2024, 2, 1),
+            NaiveDate::from_ymd_opt(2024, 2, 29),
+        )),
+        ..bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor)
+    };
+
+    let cash = CashBasisEntries::new(&bills, &config);
+
+    assert!(cash.unmatched.is_empty());
+    assert_eq!(cash.entries.len(), 1);
+    let entry = cash.entries[0];
+    assert_eq!(
+        entry.bill.total() * entry.proportion,
+        Decimal::new(580000, 2)
+    );
+}
+
+#[test]
+fn cash_basis_uses_the_payment_exchange_rate() {
+    let mut invoice = read_bill("factura_ppd.xml");
+    invoice.currency = "USD".to_string();
+    invoice.exchange_rate = Some(Decimal::new(1700, 2));
+
+    let mut payment = read_bill("pago.xml");
+    let payments = payment
+        .complement
+        .payments
+        .as_mut()
+        .expect("Complemento de pagos no encontrado");
+    for p in &mut payments.list {
+        p.currency = "USD".to_string();
+        p.exchange_rate = Some(Decimal::new(1850, 2));
+        for document in &mut p.related_documents {
+            document.currency = "USD".to_string();
+        }
+    }
+
+    let bills = vec![invoice, payment];
+    let config = bill_extractor::Config {
+        dates: DateConfig::from((
+            NaiveDate::from_ymd_opt(2024, 2, 1),
+            NaiveDate::from_ymd_opt(2024, 2, 29),
+        )),
+        ..bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor)
+    };
+
+    let cash = CashBasisEntries::new(&bills, &config);
+
+    assert_eq!(cash.entries.len(), 1);
+    assert_eq!(
+        cash.entries[0].exchange_rate().ok(),
+        Some(Decimal::new(1850, 2))
+    );
+}
+
+#[test]
+fn cash_basis_rejects_payment_receipt_type() {
+    let command = SatrCommand::try_parse_from([
+        "satr",
+        "report",
+        "emisor",
+        "EKU9003173C9",
+        "total",
+        "--flujo",
+        "--tipo",
+        "pago",
+    ])
+    .expect("Error al leer los argumentos");
+    let Action::Report(report) = command.action else {
+        panic!("Se esperaba el comando report");
+    };
+
+    assert!(matches!(
+        ReporterFactory::from_subject(report.subject),
+        Err(SatrError::InvalidOptions(_))
+    ));
+}
+
+#[test]
+fn isr_alias_reports_withheld_isr() {
+    let command = SatrCommand::try_parse_from(["satr", "report", "emisor", "EKU9003173C9", "isr"])
+        .expect("Error al leer los argumentos");
+    let Action::Report(report) = command.action else {
+        panic!("Se esperaba el comando report");
+    };
+
+    assert!(matches!(
+        report.subject.sub_command(),
+        ReportTypeSubCommand::IsrRetenido(_)
+    ));
+}
+
+#[test]
+fn concept_taxes_deserialize_works() {
+    let bill = read_bill("factura.xml");
+    let concept = &bill.concepts.list[0];
+
+    assert_eq!(concept.id_number.as_deref(), Some("SRV-01"));
+    assert_eq!(concept.tax_object.as_deref(), Some("02"));
+    assert_eq!(
+        concept.taxes.carried_forward_total(),
+        Decimal::new(160000, 2)
+    );
+    assert_eq!(concept.taxes.withheld_total(), Decimal::new(206667, 2));
+}
+
+#[test]
+fn discounts_are_reconciled() {
+    let bill = read_bill("factura.xml");
+    assert!(bill.reconciles());
+
+    let mut file_path = assets_path();
+    file_path.push("factura.xml");
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let discounted = file.replace(
+        r#"SubTotal="10000.00""#,
+        r#"SubTotal="10000.00" Descuento="500.00""#,
+    );
+    let bill = Bill::from_xml(&discounted).expect("Error al deserializar el XML");
+
+    assert_eq!(bill.discount(), Decimal::new(50000, 2));
+    assert_eq!(bill.subtotal(), Decimal::new(950000, 2));
+    assert_eq!(bill.computed_total(), Decimal::new(903333, 2));
+    assert!(!bill.reconciles());
+}
+
+#[test]
+fn extract_reports_skipped_files() {
+    let dir = TempDir::new("skipped");
+    std::fs::write(dir.join("roto.xml"), "<cfdi:Comprobante Version=\"4.0\">")
+        .expect("Error al escribir el archivo XML");
+
+    let config = bill_extractor::Config::new("EKU9003173C9", SubjectType::Emisor);
+    let extraction = BillExtractor::new(Cow::Owned(config))
+        .extract(&dir)
+        .expect("Error al extraer las facturas");
+
+    assert!(extraction.bills.is_empty());
+    assert_eq!(extraction.skipped.len(), 1);
+    assert_eq!(
+        extraction.skipped[0].origin,
+        Origin::File(dir.join("roto.xml"))
+    );
+}
+
+#[test]
+fn check_classifies_files() {
+    let dir = duplicated_zips_dir("check");
+    std::fs::write(dir.join("otro.xml"), "<Catalogo Version=\"1.0\"/>")
+        .expect("Error al escribir el archivo XML");
+    std::fs::write(dir.join("roto.xml"), "<cfdi:Comprobante Version=\"4.0\"/>")
+        .expect("Error al escribir el archivo XML");
+
+    std::fs::write(dir.join("roto.zip"), "no es un zip").expect("Error al escribir el zip");
+
+    let checks = Checker::check(&dir, 2).expect("Error al revisar los archivos");
+    let count = |f: fn(&CheckStatus) -> bool| checks.iter().filter(|c| f(&c.status)).count();
+
+    assert_eq!(checks.len(), 5);
+    assert_eq!(count(|s| matches!(s, CheckStatus::Valid)), 1);
+    assert_eq!(count(|s| matches!(s, CheckStatus::Duplicate)), 1);
+    assert_eq!(count(|s| matches!(s, CheckStatus::Rejected(_))), 2);
+    assert_eq!(count(|s| matches!(s, CheckStatus::NotCfdi)), 1);
+    assert!(checks
+        .iter()
+        .any(|c| matches!(c.status, CheckStatus::Rejected(_))
+            && c.origin == Some(Origin::File(dir.join("roto.zip")))));
+
+    // Con índice el resultado es el mismo.
+    Index::default()
+        .save(&dir)
+        .expect("Error al crear el índice");
+    assert_eq!(
+        Checker::check(&dir, 1)
+            .expect("Error al revisar los archivos")
+            .len(),
+        5
+    );
+}
+
+#[test]
+fn relation_graph_works() {
+    let mut file_path = assets_path();
+    file_path.push("factura_ieps.xml");
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let substitute = file.replace(
+        "<cfdi:Emisor",
+        r#"<cfdi:CfdiRelacionados TipoRelacion="04"><cfdi:CfdiRelacionado UUID="6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"/></cfdi:CfdiRelacionados><cfdi:Emisor"#,
+    );
+
+    let bills = vec![
+        read_bill("factura.xml"),
+        read_bill("nota_credito.xml"),
+        Bill::from_xml(&substitute).expect("Error al deserializar el XML"),
+    ];
+    let graph = RelationGraph::new(&bills);
+
+    assert_eq!(
+        bills[1].relations[0].relation_type,
+        RelationType::NotaCredito
+    );
+    let original = graph
+        .credit_note_original(&bills[1])
+        .expect("Factura original no encontrada");
+    assert_eq!(original.uuid(), bills[0].uuid());
+    assert_eq!(
+        graph.incoming("6f1b2c3d-4e5f-4a7b-8c9d-0e1f2a3b4c5d").len(),
+        2
+    );
+    assert!(graph.is_substituted(&bills[0]));
+    assert!(!graph.is_substituted(&bills[1]));
+}
+
+#[test]
+fn default_config_extracts_every_rfc() {
+    let extraction = BillExtractor::new(Cow::Owned(bill_extractor::Config::default()))
+        .extract(assets_path())
+        .expect("Error al extraer las facturas");
+    let graph = RelationGraph::new(&extraction.bills);
+
+    assert!(extraction.skipped.is_empty());
+    let note = graph
+        .get("D4E5F6A7-B8C9-4D0E-9F1A-2B3C4D5E6F70")
+        .expect("Nota de crédito no encontrada");
+    assert!(graph.credit_note_original(note).is_some());
+    assert!(extraction
+        .bills
+        .iter()
+        .any(|b| b.emisor.rfc != note.emisor.rfc));
+}
+
+#[test]
+fn cancellation_list_parse_works() {
+    let mut file_path = assets_path();
+    file_path.push("metadata.txt");
+    let metadata = CancellationList::from_path(file_path).expect("Error al leer los metadatos");
+
+    assert!(metadata.is_cancelled("d4e5f6a7-b8c9-4d0e-9f1a-2b3c4d5e6f70"));
+    assert!(!metadata.is_cancelled("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"));
+
+    let csv = "UUID,Estatus\n6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D,Cancelado\n\
+               B7E1C2A9-3F4D-4E6B-8A1C-9D2E3F4A5B6C,Vigente\n\
+               A1B2C3D4-E5F6-4711-9A8B-7C6D5E4F3A21\n";
+    let list = CancellationList::parse(csv).expect("Error al leer la lista");
+    assert!(list.is_cancelled("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"));
+    assert!(!list.is_cancelled("B7E1C2A9-3F4D-4E6B-8A1C-9D2E3F4A5B6C"));
+    assert!(list.is_cancelled("A1B2C3D4-E5F6-4711-9A8B-7C6D5E4F3A21"));
+
+    assert_eq!(CancellationList::parse("no es un uuid").err(), Some(1));
+
+    let csv = "uuid,rfc,status\n6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D,EKU9003173C9,vigente\n\
+               B7E1C2A9-3F4D-4E6B-8A1C-9D2E3F4A5B6C,EKU9003173C9,cancelado\n";
+    let list = CancellationList::parse(csv).expect("Error al leer la lista");
+    assert!(!list.is_cancelled("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"));
+    assert!(list.is_cancelled("B7E1C2A9-3F4D-4E6B-8A1C-9D2E3F4A5B6C"));
+
+    let csv = "RFC;UUID\nEKU9003173C9;6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D\n";
+    let list = CancellationList::parse(csv).expect("Error al leer la lista");
+    assert!(list.is_cancelled("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D"));
+
+    // Sin encabezado no puede saberse cuál columna es el estatus.
+    let csv = "6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D,EKU9003173C9,vigente\n";
+    assert_eq!(CancellationList::parse(csv).err(), Some(1));
+}
+
+#[test]
+fn extract_separates_cancelled_bills() {
+    let list = CancellationList::parse("D4E5F6A7-B8C9-4D0E-9F1A-2B3C4D5E6F70")
+        .expect("Error al leer la lista");
+    let config = bill_extractor::Config {
+        cancellations: Some(Arc::new(list)),
+        ..bill_extractor::Config::new("URE180429TM6", SubjectType::Receptor)
+    };
+
+    let extraction = BillExtractor::new(Cow::Owned(config))
+        .extract(assets_path())
+        .expect("Error al extraer las facturas");
+
+    assert_eq!(extraction.cancelled.len(), 1);
+    assert_eq!(
+        extraction.cancelled[0].uuid(),
+        Some("D4E5F6A7-B8C9-4D0E-9F1A-2B3C4D5E6F70")
+    );
+    assert!(extraction
+        .bills
+        .iter()
+        .all(|b| b.receipt_type != ReceiptType::Egreso));
+}
+
+#[test]
+fn payroll_complement_works() {
+    let bill = read_bill("nomina.xml");
+
+    assert_eq!(bill.receipt_type, ReceiptType::Nomina);
+    assert!(bill.reconciles());
+
+    let payroll = bill
+        .complement
+        .payroll
+        .first()
+        .expect("Nómina no encontrada");
+    assert_eq!(payroll.version, "1.2");
+    assert_eq!(payroll.paid_days, Decimal::new(16, 0));
+    assert_eq!(payroll.employee.employee_number, "120");
+    assert_eq!(payroll.taxed(), Decimal::new(1400000, 2));
+    assert_eq!(payroll.exempt(), Decimal::new(100000, 2));
+    assert_eq!(payroll.isr_withheld(), Decimal::new(208050, 2));
+    assert_eq!(bill.gross_salary(), Decimal::new(1500000, 2));
+}
+
+#[test]
+fn payroll_with_several_complements_works() {
+    let xml = std::fs::read_to_string(assets_path().join("nomina.xml"))
+        .expect("Error al leer el archivo XML");
+    let start = xml.find("<nomina12:Nomina ").expect("Nómina no encontrada");
+    let end = xml
+        .find("</nomina12:Nomina>")
+        .expect("Nómina no encontrada")
+        + 18;
+    let extraordinary = xml[start..end].replace(r#"TipoNomina="O""#, r#"TipoNomina="E""#);
+    let xml = format!("{}{extraordinary}{}", &xml[..end], &xml[end..]);
+
+    let bill = Bill::from_xml(&xml).expect("Error al deserializar el XML");
+    assert_eq!(bill.complement.payroll.len(), 2);
+    assert_eq!(bill.gross_salary(), Decimal::new(3000000, 2));
+
+    let dir = TempDir::new("payroll");
+    std::fs::write(dir.join("nomina.xml"), &xml).expect("Error al escribir el XML");
+    let report = |report_type: &str| {
+        let command = SatrCommand::try_parse_from([
+            "satr",
+            "report",
+            "receptor",
+            "XOJI740919U48",
+            report_type,
+        ])
+        .expect("Error al leer los argumentos");
+        let Action::Report(report) = command.action else {
+            panic!("Se esperaba el comando report");
+        };
+
+        ReporterFactory::from_subject(report.subject)
+            .expect("Error al crear el reporte")
+            .money_report(&dir)
+            .expect("Error al generar el reporte")
+    };
+
+    let payroll = report("nomina");
+    assert_eq!(payroll.result, Decimal::new(3000000, 2));
+    assert_eq!(payroll.by_payroll.len(), 1);
+    assert_eq!(payroll.by_payroll[0].receipts, 1);
+    assert_eq!(payroll.by_payroll[0].gross, Decimal::new(3000000, 2));
+    assert_eq!(payroll.by_payroll[0].isr_withheld, Decimal::new(416100, 2));
+
+    assert!(report("total").by_payroll.is_empty());
+}
+
+#[test]
+fn payroll_is_only_reported_by_the_payroll_report() {
+    let dir = TempDir::new("mixed_receipts");
+    for name in ["factura.xml", "nota_credito.xml"] {
+        std::fs::copy(assets_path().join(name), dir.join(name)).expect("Error al copiar el XML");
+    }
+    let payroll = std::fs::read_to_string(assets_path().join("nomina.xml"))
+        .expect("Error al leer el archivo XML")
+        .replace(
+            r#"<cfdi:Emisor Rfc="IIA040805DZ4""#,
+            r#"<cfdi:Emisor Rfc="EKU9003173C9""#,
+        );
+    std::fs::write(dir.join("nomina.xml"), payroll).expect("Error al escribir el XML");
+
+    let report = |report_type: &str| {
+        let command =
+            SatrCommand::try_parse_from(["satr", "report", "emisor", "EKU9003173C9", report_type])
+                .expect("Error al leer los argumentos");
+        let Action::Report(report) = command.action else {
+            panic!("Se esperaba el comando report");
+        };
+
+        ReporterFactory::from_subject(report.subject)
+            .expect("Error al crear el reporte")
+            .money_report(&dir)
+            .expect("Error al generar el reporte")
+            .result
+    };
+
+    assert_eq!(ReceiptType::Nomina.sign(), None);
+    assert_eq!(report("total"), Decimal::new(837333, 2));
+    assert_eq!(report("subtotal"), Decimal::new(900000, 2));
+    assert_eq!(report("nomina"), Decimal::new(1500000, 2));
+}
+
+#[test]
+fn local_taxes_reconcile() {
+    let bill = read_bill("factura_hospedaje.xml");
+
+    let local = bill
+        .complement
+        .local_taxes
+        .as_ref()
+        .expect("Impuestos locales no encontrados");
+    assert_eq!(local.carried_forward[0].name, "ISH");
+    assert!(local.withheld.is_empty());
+    assert_eq!(bill.local_taxes_carried_forward(), Decimal::new(6000, 2));
+    assert_eq!(bill.local_taxes(), Decimal::new(6000, 2));
+    assert_eq!(bill.computed_total(), Decimal::new(238000, 2));
+    assert!(bill.reconciles());
+}
+
+#[test]
+fn retention_platforms_works() {
+    let mut file_path = assets_path();
+    file_path.push("retencion_plataforma.xml");
+    let file = std::fs::read_to_string(file_path).expect("Error al leer el archivo XML");
+    let retention = Retention::from_xml(&file).expect("Error al deserializar el XML");
+
+    assert_eq!(retention.recipient_rfc(), Some("XOJI740919U48"));
+    assert_eq!(retention.period.fiscal_year, 2024);
+    assert_eq!(retention.isr_withheld(), Decimal::new(21000, 2));
+    assert_eq!(retention.iva_withheld(), Decimal::new(80000, 2));
+
+    let platforms = retention
+        .complement
+        .platforms
+        .as_ref()
+        .expect("Complemento de plataformas no encontrado");
+    assert_eq!(platforms.platform_fees, Decimal::new(250000, 2));
+    assert_eq!(platforms.iva_carried_forward, Decimal::new(160000, 2));
+}
+
+#[test]
+fn retention_extractor_groups_by_retainer() {
+    let extractor = RetentionExtractor::new(retention_extractor::Config {
+        recipient_rfc: "XOJI740919U48".to_string(),
+        fiscal_year: None,
+        deduplicate: true,
+    });
+    let extraction = extractor
+        .extract(assets_path())
+        .expect("Error al extraer las constancias");
+
+    assert_eq!(extraction.retentions.len(), 2);
+    assert!(extraction.skipped.is_empty());
+
+    let totals = RetentionTotal::by_retainer(&extraction.retentions);
+    assert_eq!(totals[0].retainer_rfc, "BNM840515VB1");
+    assert_eq!(totals[0].isr_withheld, Decimal::new(7500, 2));
+    assert_eq!(totals[0].exempt, Decimal::new(380000, 2));
+    assert_eq!(totals[1].iva_withheld, Decimal::new(80000, 2));
+    assert_eq!(totals[1].platform_fees, Decimal::new(250000, 2));
+    assert!(totals[0].iva_carried_forward.is_zero());
+
+    let config = bill_extractor::Config::new("XOJI740919U48", SubjectType::Receptor);
+    let bills = BillExtractor::new(Cow::Owned(config))
+        .extract(assets_path())
+        .expect("Error al extraer las facturas");
+    assert_eq!(bills.bills.len(), 1);
+    assert!(bills.skipped.is_empty());
+}
+
+#[test]
+fn iter_xmls_is_lazy() {
+    let dir = duplicated_zips_dir("lazy");
+    let mut sources = XmlExtractor::iter_xmls(&dir).expect("Error al leer la carpeta");
+
+    let first = sources
+        .next()
+        .expect("Sin archivos")
+        .expect("Error al leer el archivo");
+    assert!(matches!(first.origin, Origin::Archive { .. }));
+
+    // El resto de los archivos se lee hasta que se solicita.
+    std::fs::remove_dir_all(&*dir).ok();
+    assert!(matches!(
+        sources.next(),
+        Some(Err(SkippedFile {
+            error: SatrError::Io(..),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn ordered_map_keeps_order() {
+    let squares: Vec<u64> = OrderedMap::new(0..1000u64, 4, |n| n * n).collect();
+    assert_eq!(squares, (0..1000u64).map(|n| n * n).collect::<Vec<_>>());
+
+    let uuids = |jobs| {
+        let config = bill_extractor::Config {
+            deduplicate: false,
+            jobs,
+            ..bill_extractor::Config::new("URE180429TM6", SubjectType::Receptor)
+        };
+
+        BillExtractor::new(Cow::Owned(config))
+            .extract(assets_path())
+            .expect("Error al extraer las facturas")
+            .bills
+            .iter()
+            .map(|b| b.uuid().unwrap_or_default().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(uuids(1), uuids(4));
+}
+
+/// Facturas de prueba que cubren los complementos guardados en el índice.
+const INDEXED_ASSETS: [&str; 8] = [
+    "factura.zip",
+    "factura_33.xml",
+    "factura_hospedaje.xml",
+    "factura_ieps.xml",
+    "factura_ppd.xml",
+    "nomina.xml",
+    "nota_credito.xml",
+    "pago.xml",
+];
+
+#[test]
+fn index_keeps_bills_as_parsed() {
+    let dir = TempDir::new("index-roundtrip");
+    for name in INDEXED_ASSETS {
+        std::fs::copy(assets_path().join(name), dir.join(name)).expect("Error al copiar");
+    }
+
+    let parsed: Vec<Bill> = XmlExtractor::iter_xmls(&dir)
+        .expect("Error al leer la carpeta")
+        .map(|source| source.and_then(Bill::from_source))
+        .collect::<Result<_, _>>()
+        .expect("Error al leer las facturas");
+
+    Index::sync(&dir, 1).expect("Error al crear el índice");
+    let indexed: Vec<Bill> = Index::sync(&dir, 1)
+        .expect("Error al leer el índice")
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("Error al leer las facturas del índice");
+
+    assert_eq!(indexed, parsed);
+}
+
+#[test]
+fn index_format_matches_its_version() {
+    let mut hash = Fnv::new();
+    for name in INDEXED_ASSETS {
+        let bill = match name.strip_suffix(".zip") {
+            Some(_) => Bill::from_source(
+                XmlExtractor::read_file(assets_path().join(name))
+                    .next()
+                    .expect("Zip vacío")
+                    .expect("Error al leer el zip"),
+            )
+            .expect("Error al deserializar el XML"),
+            None => read_bill(name),
+        };
+        let json = serde_json::to_string(&bill).expect("Error al serializar la factura");
+        hash.update(json.as_bytes());
+    }
+
+    // Si el formato de las facturas cambia, los índices guardados dejan de leerse:
+    // incrementar INDEX_VERSION y actualizar el hash.
+    assert_eq!((INDEX_VERSION, hash.finish()), (3, 0x5d9c7f1c86416da9));
+}
+
+#[test]
+fn unwritable_index_only_fails_on_rebuild() {
+    let dir = TempDir::new("index_unwritable");
+    std::fs::copy(assets_path().join("factura.xml"), dir.join("a.xml"))
+        .expect("Error al copiar el XML");
+    // Un directorio en la ruta temporal impide escribir el índice.
+    std::fs::create_dir(Index::path(&dir).with_extension("json.tmp"))
+        .expect("Error al crear la carpeta");
+
+    let bills = Index::sync(&dir, 1).expect("Error al leer la carpeta");
+    assert_eq!(bills.len(), 1);
+    assert!(bills[0].is_ok());
+    assert!(!Index::exists(&dir));
+
+    assert!(matches!(
+        Index::rebuild(&dir, 1),
+        Err(SatrError::Io(path, _)) if path == Index::path(&dir).with_extension("json.tmp")
+    ));
+}
+
+#[test]
+fn index_keeps_unreadable_entries() {
+    let dir = duplicated_zips_dir("index-errors");
+    std::fs::write(dir.join("roto.xml"), "<cfdi:Comprobante Version=\"4.0\">")
+        .expect("Error al escribir el archivo XML");
+    let skipped = |bills: Vec<Result<Bill, SkippedFile>>| {
+        bills
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>()
+    };
+
+    let first = skipped(Index::sync(&dir, 1).expect("Error al crear el índice"));
+    assert_eq!(first.len(), 1);
+
+    let status = Index::status(&dir).expect("Error al revisar el índice");
+    assert!(status.is_up_to_date());
+    assert_eq!((status.bills, status.skipped), (2, 1));
+
+    let saved = std::fs::metadata(Index::path(&dir))
+        .and_then(|m| m.modified())
+        .expect("Error al leer el índice");
+    let second = skipped(Index::sync(&dir, 1).expect("Error al leer el índice"));
+    assert_eq!(second[0].origin, Origin::File(dir.join("roto.xml")));
+    assert_eq!(second[0].error.to_string(), first[0].error.to_string());
+
+    // Sin cambios en la carpeta el índice no se vuelve a escribir.
+    let unchanged = std::fs::metadata(Index::path(&dir))
+        .and_then(|m| m.modified())
+        .expect("Error al leer el índice");
+    assert_eq!(saved, unchanged);
+}
+
+#[test]
+fn index_is_updated_incrementally() {
+    let dir = duplicated_zips_dir("index");
+    let uuids = |bills: Vec<Result<Bill, _>>| {
+        bills
+            .into_iter()
+            .map(|b| {
+                b.expect("Error al leer la factura")
+                    .uuid()
+                    .map(str::to_string)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let bills = Index::sync(&dir, 2).expect("Error al crear el índice");
+    assert!(Index::exists(&dir));
+    assert_eq!(bills.len(), 2);
+    assert!(matches!(
+        &bills[0].as_ref().map(|b| b.source.clone()),
+        Ok(Some(Origin::Archive { path, .. })) if path == &dir.join("a.zip")
+    ));
+
+    std::fs::copy(assets_path().join("nota_credito.xml"), dir.join("c.xml"))
+        .expect("Error al copiar el XML");
+    std::fs::remove_file(dir.join("b.zip")).expect("Error al eliminar el zip");
+    let status = Index::status(&dir).expect("Error al revisar el índice");
+    assert_eq!((status.files, status.unindexed, status.removed), (2, 1, 1));
+
+    let bills = uuids(Index::sync(&dir, 1).expect("Error al actualizar el índice"));
+    assert_eq!(
+        bills,
+        [
+            Some("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D".to_string()),
+            Some("D4E5F6A7-B8C9-4D0E-9F1A-2B3C4D5E6F70".to_string())
+        ]
+    );
+    assert!(Index::status(&dir)
+        .expect("Error al revisar el índice")
+        .is_up_to_date());
+
+    let config = bill_extractor::Config {
+        receipt_types: vec![ReceiptType::Egreso],
+        ..bill_extractor::Config::new("URE180429TM6", SubjectType::Receptor)
+    };
+    let extraction = BillExtractor::new(Cow::Owned(config))
+        .extract(&dir)
+        .expect("Error al extraer las facturas");
+    assert_eq!(extraction.bills.len(), 1);
+    assert!(extraction.bills[0].reconciles());
+}
+
+#[test]
+fn nested_archives_are_traversed() {
+    use std::io::Write;
+
+    let dir = TempDir::new("archives");
+    let asset = |name: &str| std::fs::read(assets_path().join(name)).expect("Error al leer");
+
+    let mut outer = zip::ZipWriter::new(std::fs::File::create(dir.join("a.zip")).unwrap());
+    outer
+        .start_file("interno.zip", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    outer.write_all(&asset("factura.zip")).unwrap();
+    outer.finish().unwrap();
+
+    let long_name = format!("{}/factura_33.xml", "2024".repeat(30));
+    let gz = flate2::write::GzEncoder::new(
+        std::fs::File::create(dir.join("b.tar.gz")).unwrap(),
+        flate2::Compression::default(),
+    );
+    let mut tar = tar::Builder::new(gz);
+    let contents = asset("factura_33.xml");
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    tar.append_data(&mut header, &long_name, contents.as_slice())
+        .unwrap();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(4);
+    tar.append_data(&mut header, "notas.txt", "hola".as_bytes())
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let mut gz = flate2::write::GzEncoder::new(
+        std::fs::File::create(dir.join("c.xml.gz")).unwrap(),
+        flate2::Compression::default(),
+    );
+    gz.write_all(&asset("nomina.xml")).unwrap();
+    gz.finish().unwrap();
+
+    let entries: Vec<_> = XmlExtractor::iter_xmls(&dir)
+        .expect("Error al leer la carpeta")
+        .map(
+            |source| match source.expect("Error al leer el archivo").origin {
+                Origin::Archive { entry, .. } => entry,
+                Origin::File(path) => panic!("{} no está comprimido", path.display()),
+            },
+        )
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            "interno.zip:6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D.xml".to_string(),
+            long_name,
+            "c.xml".to_string()
+        ]
+    );
+}
+
+#[test]
+fn upper_case_extensions_are_supported() {
+    use std::io::Write;
+
+    let dir = TempDir::new("upper-case");
+    let asset = |name: &str| std::fs::read(assets_path().join(name)).expect("Error al leer");
+
+    std::fs::write(dir.join("A.XML"), asset("factura.xml")).expect("Error al escribir el XML");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(dir.join("B.ZIP")).unwrap());
+    zip.start_file("NOTA.XML", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(&asset("nota_credito.xml")).unwrap();
+    zip.finish().unwrap();
+    let mut gz = flate2::write::GzEncoder::new(
+        std::fs::File::create(dir.join("C.XML.GZ")).unwrap(),
+        flate2::Compression::default(),
+    );
+    gz.write_all(&asset("nomina.xml")).unwrap();
+    gz.finish().unwrap();
+    let eml = String::from_utf8(asset("correo.eml"))
+        .unwrap()
+        .replace(
+            r#"filename="factura_hospedaje.xml""#,
+            r#"filename="HOSPEDAJE.XML""#,
+        )
+        .replace(r#"filename="pago.zip""#, r#"filename="PAGO.ZIP""#);
+    std::fs::write(dir.join("D.EML"), eml).expect("Error al escribir el correo");
+
+    let entries: Vec<_> = XmlExtractor::iter_xmls(&dir)
+        .expect("Error al leer la carpeta")
+        .map(
+            |source| match source.expect("Error al leer el archivo").origin {
+                Origin::Archive { entry, .. } => entry,
+                Origin::File(path) => path.file_name().unwrap().to_string_lossy().to_string(),
+            },
+        )
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            "A.XML",
+            "NOTA.XML",
+            "C.XML",
+            "HOSPEDAJE.XML",
+            "PAGO.ZIP:pago.xml"
+        ]
+    );
+}
+
+#[test]
+fn truncated_tar_keeps_previous_entries() {
+    let dir = TempDir::new("truncated-tar");
+    let contents = std::fs::read(assets_path().join("factura.xml")).expect("Error al leer");
+    let mut tar = tar::Builder::new(Vec::new());
+    for name in ["a.xml", "b.xml"] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, name, contents.as_slice())
+            .unwrap();
+    }
+    let mut data = tar.into_inner().unwrap();
+    // Corta el archivo a la mitad del contenido de la segunda entrada.
+    data.truncate(512 * 2 + contents.len().next_multiple_of(512) + contents.len() / 2);
+    std::fs::write(dir.join("facturas.tar"), data).expect("Error al escribir el tar");
+
+    let sources: Vec<_> = XmlExtractor::read_file(dir.join("facturas.tar")).collect();
+    assert_eq!(sources.len(), 2);
+    assert!(matches!(
+        &sources[0],
+        Ok(XmlSource { origin: Origin::Archive { entry, .. }, .. }) if entry == "a.xml"
+    ));
+    assert!(matches!(
+        &sources[1],
+        Err(SkippedFile {
+            error: SatrError::Io(..),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn tar_entries_are_read_on_demand() {
+    struct CountingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        read: Arc<AtomicUsize>,
+    }
+
+    impl std::io::Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read.fetch_add(n, Ordering::SeqCst);
+            Ok(n)
+        }
+    }
+
+    impl std::io::Seek for CountingReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    let contents = std::fs::read(assets_path().join("factura.xml")).expect("Error al leer");
+    let mut tar = tar::Builder::new(Vec::new());
+    for i in 0..100 {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, format!("{i}.xml"), contents.as_slice())
+            .unwrap();
+    }
+    let data = tar.into_inner().unwrap();
+    let size = data.len();
+
+    let read = Arc::new(AtomicUsize::new(0));
+    let reader = CountingReader {
+        inner: std::io::Cursor::new(data),
+        read: read.clone(),
+    };
+    let mut entries = Tar
+        .entries(Box::new(reader), "facturas.tar", Path::new("facturas.tar"))
+        .expect("Error al abrir el tar");
+
+    let first = entries.next().expect("Entrada no encontrada");
+    assert_eq!(first.expect("Error al leer la entrada").name, "0.xml");
+    assert!(read.load(Ordering::SeqCst) < size / 10);
+    assert_eq!(entries.count(), 99);
+}
+
+#[test]
+fn mail_attachments_are_extracted() {
+    let dir = TempDir::new("mail");
+    let eml = std::fs::read(assets_path().join("correo.eml")).expect("Error al leer el correo");
+
+    let maildir = dir.join("Maildir");
+    for sub in ["cur", "new", "tmp"] {
+        std::fs::create_dir_all(maildir.join(sub)).expect("Error al crear el Maildir");
+    }
+    std::fs::write(maildir.join("cur").join("1721061000.M1P1.host:2,S"), &eml)
+        .expect("Error al escribir el correo");
+
+    let mut mbox = Vec::new();
+    for _ in 0..2 {
+        mbox.extend_from_slice(b"From facturas@hotel.example Mon Jul 15 10:30:00 2024\r\n");
+        mbox.extend_from_slice(&eml);
+        mbox.extend_from_slice(b"\r\n");
+    }
+    std::fs::write(dir.join("buzon.mbox"), mbox).expect("Error al escribir el buzón");
+    std::fs::copy(assets_path().join("correo.eml"), dir.join("correo.eml"))
+        .expect("Error al copiar el correo");
+
+    let sources: Vec<_> = XmlExtractor::iter_xmls(&dir)
+        .expect("Error al leer la carpeta")
+        .map(|source| source.expect("Error al leer el correo").origin.to_string())
+        .collect();
+    let path = |name: &str| dir.join(name).display().to_string();
+    let maildir_message = path("Maildir/cur/1721061000.M1P1.host:2,S");
+    assert_eq!(
+        sources,
+        [
+            format!("{maildir_message}:factura_hospedaje.xml"),
+            format!("{maildir_message}:pago.zip:pago.xml"),
+            format!("{}:1.eml:factura_hospedaje.xml", path("buzon.mbox")),
+            format!("{}:1.eml:pago.zip:pago.xml", path("buzon.mbox")),
+            format!("{}:2.eml:factura_hospedaje.xml", path("buzon.mbox")),
+            format!("{}:2.eml:pago.zip:pago.xml", path("buzon.mbox")),
+            format!("{}:factura_hospedaje.xml", path("correo.eml")),
+            format!("{}:pago.zip:pago.xml", path("correo.eml")),
+        ]
+    );
+
+    let bill = Bill::from_source(
+        XmlExtractor::read_file(dir.join("correo.eml"))
+            .next()
+            .expect("El correo no tiene adjuntos")
+            .expect("Error al leer el correo"),
+    )
+    .expect("Error al deserializar la factura adjunta");
+    assert_eq!(bill.uuid(), Some("F1A2B3C4-D5E6-4F70-8192-A3B4C5D6E7F8"));
+}
+
+#[test]
+fn metadata_reconcile_works() {
+    let metadata = Metadata::from_path(assets_path().join("metadata.txt"))
+        .expect("Error al leer los metadatos");
+    assert_eq!(metadata.records.len(), 5);
+
+    let credit_note = &metadata.records[1];
+    assert_eq!(credit_note.receipt_type, ReceiptType::Egreso);
+    assert_eq!(credit_note.status, MetadataStatus::Cancelado);
+    assert_eq!(credit_note.amount, Decimal::new(116000, 2));
+    assert!(credit_note.cancellation_date.is_some());
+    assert_eq!(
+        Metadata::parse("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D~EKU9003173C9~1").err(),
+        Some(1)
+    );
+
+    // Las descargas recientes agregan columnas de terceros al final.
+    let with_third_parties = Metadata::parse(
+        "Uuid~RfcEmisor~NombreEmisor~RfcReceptor~NombreReceptor~RfcPac~FechaEmision~\
+         FechaCertificacionSat~Monto~EfectoComprobante~Estatus~FechaCancelacion~\
+         RfcACuentaTerceros~NombreACuentaTerceros\n\
+         6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D~EKU9003173C9~ESCUELA KEMPER URGATE~\
+         URE180429TM6~UNIVERSIDAD ROBOTICA ESPAÑOLA~SPR190613I52~2024-03-15 10:30:00~\
+         2024-03-15 10:31:00~11600.00~I~1~~XIA190128J61~XENON INDUSTRIAL ARTICLES",
+    )
+    .expect("Error al leer los metadatos con terceros");
+    assert_eq!(with_third_parties.records.len(), 1);
+    assert_eq!(
+        with_third_parties.records[0].amount,
+        Decimal::new(1160000, 2)
+    );
+
+    let reconciliation =
+        Reconciler::reconcile(&metadata, assets_path(), 2).expect("Error al conciliar");
+    let uuids = |records: Vec<&str>| records.join(",");
+    assert_eq!(reconciliation.matched, 2);
+    assert_eq!(
+        uuids(
+            reconciliation
+                .missing
+                .iter()
+                .map(|r| r.uuid.as_str())
+                .collect()
+        ),
+        "9A8B7C6D-5E4F-4321-8A9B-0C1D2E3F4A5B"
+    );
+    assert_eq!(
+        uuids(
+            reconciliation
+                .cancelled
+                .iter()
+                .map(|(r, _)| r.uuid.as_str())
+                .collect()
+        ),
+        "D4E5F6A7-B8C9-4D0E-9F1A-2B3C4D5E6F70"
+    );
+    let (record, bill) = &reconciliation.mismatched[0];
+    assert_eq!(reconciliation.mismatched.len(), 1);
+    assert_eq!(
+        (record.amount, bill.total()),
+        (Decimal::new(295000, 2), Decimal::new(290000, 2))
+    );
+}
+
+/// Servidor local que simula los servicios de descarga masiva del SAT. Valida la firma de
+/// cada petición con el certificado de prueba y entrega `package` como único paquete,
+/// después de responder una vez que la solicitud sigue en proceso.
+fn sat_mock(package_id: &'static str, package: &[u8]) -> String {
+    use base64::Engine;
+    use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Sign, RsaPublicKey};
+    use sha1::{Digest, Sha1};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use x509_cert::der::{Decode, Encode};
+
+    let certificate = std::fs::read(assets_path().join("fiel_prueba.cer")).unwrap();
+    let public_key = x509_cert::Certificate::from_der(&certificate)
+        .and_then(|c| c.tbs_certificate.subject_public_key_info.to_der())
+        .map(|der| RsaPublicKey::from_public_key_der(&der).unwrap())
+        .unwrap();
+    let package = base64::engine::general_purpose::STANDARD.encode(package);
+    let package_attribute = format!(r#"IdPaquete="{package_id}""#);
+    let between = |body: &str, start: &str, end: &str| -> String {
+        let from = body.find(start).expect("Falta un elemento") + start.len();
+        body[from..from + body[from..].find(end).unwrap()].to_string()
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let mut verifications = 0;
+
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&mut stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+            let (mut length, mut authorization) = (0, String::new());
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                match line.trim_end().split_once(": ") {
+                    Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                        length = value.parse().unwrap()
+                    }
+                    Some((name, value)) if name.eq_ignore_ascii_case("authorization") => {
+                        authorization = value.to_string()
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let body = String::from_utf8(body).unwrap();
+
+            let signed_info = format!(
+                "<SignedInfo{}</SignedInfo>",
+                between(&body, "<SignedInfo", "</SignedInfo>")
+            );
+            let signature = base64::engine::general_purpose::STANDARD
+                .decode(between(&body, "<SignatureValue>", "</SignatureValue>"))
+                .unwrap();
+            let valid = public_key
+                .verify(
+                    Pkcs1v15Sign::new::<Sha1>(),
+                    &Sha1::digest(signed_info.as_bytes()),
+                    &signature,
+                )
+                .is_ok();
+            let authorized = authorization == r#"WRAP access_token="token-prueba""#;
+
+            let (status, response) = match path.as_str() {
+                _ if !valid => (500, "<faultstring>Firma inválida</faultstring>".to_string()),
+                "/Autenticacion/Autenticacion.svc" => (
+                    200,
+                    "<AutenticaResult>token-prueba</AutenticaResult>".to_string(),
+                ),
+                _ if !authorized => (500, "<faultstring>Token inválido</faultstring>".to_string()),
+                "/SolicitaDescargaService.svc" if body.contains(r#"RfcReceptor="URE180429TM6""#) => (
+                    200,
+                    r#"<SolicitaDescargaRecibidosResult IdSolicitud="4E3D2C1B-0A9F-4E8D-8C7B-6A5F4E3D2C1B" CodEstatus="5000" Mensaje="Solicitud Aceptada"/>"#
+                        .to_string(),
+                ),
+                "/VerificaSolicitudDescargaService.svc" => {
+                    verifications += 1;
+                    let state = if verifications == 1 { 2 } else { 3 };
+                    (
+                        200,
+                        format!(
+                            r#"<VerificaSolicitudDescargaResult CodEstatus="5000" EstadoSolicitud="{state}" CodigoEstadoSolicitud="5000" NumeroCFDIs="1" Mensaje="Solicitud Aceptada"><IdsPaquetes>{package_id}</IdsPaquetes></VerificaSolicitudDescargaResult>"#
+                        ),
+                    )
+                }
+                "/DescargaMasivaService.svc" if body.contains(&package_attribute) => (
+                    200,
+                    format!(
+                        r#"<h:respuesta xmlns:h="http://DescargaMasivaTerceros.sat.gob.mx" CodEstatus="5000" Mensaje="Solicitud Aceptada"/><Paquete>{package}</Paquete>"#
+                    ),
+                ),
+                _ => (500, "<faultstring>Petición inválida</faultstring>".to_string()),
+            };
+
+            let response = format!(
+                r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body>{response}</s:Body></s:Envelope>"#
+            );
+            write!(
+                stream,
+                "HTTP/1.1 {status} OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+
+    format!("http://{address}")
+}
+
+/// Descargador de facturas recibidas que usa [`sat_mock`] con la e.firma de prueba.
+fn mock_downloader(
+    output: &Path,
+    request_type: RequestType,
+    package_id: &'static str,
+    package: &[u8],
+) -> Downloader {
+    let config = downloader::Config {
+        rfc: "URE180429TM6".to_string(),
+        subject: SubjectType::Receptor,
+        start: NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .unwrap(),
+        end: NaiveDate::from_ymd_opt(2024, 12, 31)
+            .and_then(|d| d.and_hms_opt(23, 59, 59))
+            .unwrap(),
+        request_type,
+        endpoints: Endpoints::with_base(&sat_mock(package_id, package)),
+        poll_interval: std::time::Duration::from_millis(10),
+        timeout: std::time::Duration::from_secs(5),
+        output: output.to_path_buf(),
+    };
+    let fiel = Fiel::from_files(
+        &assets_path().join("fiel_prueba.cer"),
+        &assets_path().join("fiel_prueba.key"),
+        "12345678a",
+    )
+    .expect("Error al leer la e.firma");
+
+    Downloader::new(config, fiel)
+}
+
+#[test]
+fn download_works_against_mock() {
+    let cer = assets_path().join("fiel_prueba.cer");
+    let key = assets_path().join("fiel_prueba.key");
+    assert!(matches!(
+        Fiel::from_files(&cer, &key, "incorrecta"),
+        Err(SatrError::Fiel(..))
+    ));
+
+    let output = TempDir::new("download");
+    let package = std::fs::read(assets_path().join("factura.zip")).unwrap();
+    let downloader = mock_downloader(&output, RequestType::Cfdi, "4E3D2C1B_01", &package);
+
+    let request_id = downloader
+        .request()
+        .expect("Error al solicitar la descarga");
+    let download = downloader
+        .download(&request_id)
+        .expect("Error al descargar los paquetes");
+    assert_eq!(download.packages, [output.join("4E3D2C1B_01.zip")]);
+    assert!(download.metadata.is_empty());
+    assert!(!output.join("4E3D2C1B_01.zip.tmp").exists());
+
+    let uuids: Vec<_> = XmlExtractor::iter_xmls(&output)
+        .expect("Error al leer la carpeta")
+        .map(|source| Bill::from_source(source.expect("Error al leer el paquete")))
+        .map(|bill| {
+            bill.expect("Error al leer la factura")
+                .uuid()
+                .map(str::to_string)
+        })
+        .collect();
+    assert_eq!(
+        uuids,
+        [Some("6F1B2C3D-4E5F-4A7B-8C9D-0E1F2A3B4C5D".to_string())]
+    );
+}
+
+#[test]
+fn download_rejects_unsafe_package_ids() {
+    let dir = TempDir::new("download-unsafe");
+    let output = dir.join("salida");
+    let package = std::fs::read(assets_path().join("factura.zip")).unwrap();
+    let downloader = mock_downloader(&output, RequestType::Cfdi, "../escape", &package);
+
+    let request_id = downloader
+        .request()
+        .expect("Error al solicitar la descarga");
+    assert!(matches!(
+        downloader.download(&request_id),
+        Err(SatrError::Http(_, message)) if message.contains("../escape")
+    ));
+    assert!(!dir.join("escape.zip").exists());
+}
+
+#[test]
+fn metadata_download_extracts_the_txt() {
+    use std::io::Write;
+
+    let output = TempDir::new("download-metadata");
+    let metadata = std::fs::read(assets_path().join("metadata.txt")).unwrap();
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    zip.start_file("4E3D2C1B_01.txt", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(&metadata).unwrap();
+    let package = zip.finish().unwrap().into_inner();
+    let downloader = mock_downloader(&output, RequestType::Metadata, "4E3D2C1B_01", &package);
+
+    let request_id = downloader
+        .request()
+        .expect("Error al solicitar la descarga");
+    let download = downloader
+        .download(&request_id)
+        .expect("Error al descargar los paquetes");
+    assert_eq!(download.packages, [output.join("4E3D2C1B_01.zip")]);
+    assert_eq!(download.metadata, [output.join("4E3D2C1B_01.txt")]);
+    assert_eq!(std::fs::read(&download.metadata[0]).unwrap(), metadata);
+    assert!(Metadata::from_path(&download.metadata[0]).is_ok());
+}
@@ -1,75 +1,323 @@
+pub mod archives;
+pub mod mail;
+
 use std::{
-    fs::File,
-    io::{BufReader, Read},
-    path::Path,
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufReader, Cursor},
+    path::{Path, PathBuf},
 };
 
+use quick_xml::{events::Event, Reader};
+
+use crate::{bill_extractor::SkippedFile, error::SatrError};
+
+use self::archives::Entries;
+
 pub struct XmlExtractor;
 
 impl XmlExtractor {
-    /// Extrae recursivamente los archivos xml de un archivo directorio.
-    pub fn load_all_xmls<P: AsRef<Path>>(path: P) -> Result<Vec<String>, String> {
-        let mut xmls = Vec::new();
+    /// Recorre recursivamente los archivos xml de un directorio y de los archivos
+    /// comprimidos que contiene, ver [`XmlExtractor::read_file`]. Cada archivo se lee hasta
+    /// que se solicita, por lo que la memoria usada no depende del tamaño de la carpeta. Los
+    /// archivos se recorren en orden alfabético. Solo falla si no puede leerse la carpeta,
+    /// los errores de cada archivo se devuelven como [`SkippedFile`].
+    pub fn iter_xmls<P: AsRef<Path>>(path: P) -> Result<XmlSources, SatrError> {
+        let root = path.as_ref();
+        Self::check_root(root)?;
 
-        for entry in walkdir::WalkDir::new(path) {
-            let entry = entry.map_err(|e| e.to_string())?;
+        Ok(XmlSources {
+            root: root.to_path_buf(),
+            walker: walkdir::WalkDir::new(root).sort_by_file_name().into_iter(),
+            file: None,
+        })
+    }
 
-            if entry.file_type().is_dir() {
-                continue;
+    /// Verifica que la carpeta, o el archivo, a recorrer exista y pueda leerse.
+    pub fn check_root(root: &Path) -> Result<(), SatrError> {
+        let metadata = fs::metadata(root).map_err(|e| SatrError::Io(root.to_path_buf(), e))?;
+        if metadata.is_dir() {
+            fs::read_dir(root).map_err(|e| SatrError::Io(root.to_path_buf(), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Indica si un archivo es xml o de algún formato de [`archives::FORMATS`].
+    pub fn is_supported(name: &str) -> bool {
+        archives::has_extension(name, ".xml") || archives::format_for(name).is_some()
+    }
+
+    /// Como [`XmlExtractor::is_supported`], incluyendo los correos de carpetas Maildir.
+    pub fn is_supported_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| archives::has_extension(&name.to_string_lossy(), ".xml"))
+            || archives::format_for_file(path).is_some()
+    }
+
+    /// Recorre los xml de un archivo: el archivo mismo si es xml, o sus entradas si es un
+    /// zip, tar, tar.gz, xml.gz, correo (.eml o Maildir) o buzón mbox, incluyendo las de
+    /// los archivos comprimidos o adjuntos dentro de él. Si el archivo o alguna entrada no
+    /// puede leerse, se devuelve como [`SkippedFile`] en su lugar.
+    pub fn read_file<P: AsRef<Path>>(path: P) -> FileSources {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut sources = FileSources {
+            path,
+            pending: None,
+            archives: Vec::new(),
+        };
+        let skipped = |path: &Path, error| SkippedFile {
+            origin: Origin::File(path.to_path_buf()),
+            error,
+        };
+
+        if let Some(format) = archives::format_for_file(&sources.path) {
+            let entries = File::open(&sources.path)
+                .map_err(|e| SatrError::Io(sources.path.clone(), e))
+                .and_then(|file| {
+                    format.entries(Box::new(BufReader::new(file)), &name, &sources.path)
+                });
+
+            match entries {
+                Ok(entries) => sources.archives.push((None, entries)),
+                Err(e) => sources.pending = Some(Err(skipped(&sources.path, e))),
             }
+        } else if archives::has_extension(&name, ".xml") {
+            let source = fs::read(&sources.path)
+                .and_then(|contents| {
+                    String::from_utf8(contents)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                })
+                .map(|contents| XmlSource {
+                    origin: Origin::File(sources.path.clone()),
+                    contents,
+                })
+                .map_err(|e| skipped(&sources.path, SatrError::Io(sources.path.clone(), e)));
+            sources.pending = Some(source);
+        }
+
+        sources
+    }
+}
+
+/// Iterador perezoso sobre los xml de un directorio, ver [`XmlExtractor::iter_xmls`]. Los
+/// archivos que no pueden leerse, o las subcarpetas que no pueden recorrerse, se devuelven
+/// como [`SkippedFile`] y se continúa con los demás.
+pub struct XmlSources {
+    root: PathBuf,
+    walker: walkdir::IntoIter,
+
+    /// Archivo cuyos xml se están leyendo.
+    file: Option<FileSources>,
+}
 
-            let file_name = entry.file_name().to_string_lossy();
+impl Iterator for XmlSources {
+    type Item = Result<XmlSource, SkippedFile>;
 
-            if file_name.ends_with(".xml") {
-                xmls.push(entry.path().to_string_lossy().to_string());
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(file) = &mut self.file {
+                match file.next() {
+                    Some(source) => return Some(source),
+                    None => self.file = None,
+                }
             }
 
-            if file_name.ends_with(".zip") {
-                let inner_xmls = Self::extract_from_zip(entry.path())?;
-                inner_xmls.iter().for_each(|xml| xmls.push(xml.to_string()));
+            let entry = match self.walker.next()? {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(&self.root).to_path_buf();
+                    return Some(Err(SkippedFile {
+                        origin: Origin::File(path),
+                        error: e.into(),
+                    }));
+                }
+            };
+
+            if entry.file_type().is_dir() || !XmlExtractor::is_supported_file(entry.path()) {
+                continue;
             }
+
+            self.file = Some(XmlExtractor::read_file(entry.path()));
+        }
+    }
+}
+
+/// Iterador sobre los xml de un archivo, ver [`XmlExtractor::read_file`]. Los archivos
+/// comprimidos se recorren en profundidad, descomprimiendo una entrada a la vez.
+pub struct FileSources {
+    path: PathBuf,
+
+    /// Xml en disco, que se devuelve tal cual, o el error al abrir el archivo.
+    pending: Option<Result<XmlSource, SkippedFile>>,
+
+    /// Archivos abiertos, del más externo al más interno, con el nombre de la entrada que
+    /// contiene a cada uno.
+    archives: Vec<(Option<String>, Entries)>,
+}
+
+impl FileSources {
+    /// Nombre completo de una entrada, con las entradas que la contienen separadas por `:`.
+    fn entry_name(&self, name: &str) -> String {
+        match self.archives.last() {
+            Some((Some(parent), _)) => format!("{parent}:{name}"),
+            _ => name.to_string(),
         }
+    }
+
+    fn entry_path(&self, entry: &str) -> PathBuf {
+        archives::entry_path(&self.path, entry)
+    }
 
-        Ok(xmls)
+    /// Ubicación de una entrada, o del archivo si no está dentro de otro.
+    fn origin(&self, entry: Option<String>) -> Origin {
+        match entry {
+            Some(entry) => Origin::Archive {
+                path: self.path.clone(),
+                entry,
+            },
+            None => Origin::File(self.path.clone()),
+        }
     }
+}
+
+impl Iterator for FileSources {
+    type Item = Result<XmlSource, SkippedFile>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(source) = self.pending.take() {
+            return Some(source);
+        }
+
+        loop {
+            let (parent, entries) = self.archives.last_mut()?;
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(error)) => {
+                    let parent = parent.clone();
+                    return Some(Err(SkippedFile {
+                        origin: self.origin(parent),
+                        error,
+                    }));
+                }
+                None => {
+                    self.archives.pop();
+                    continue;
+                }
+            };
+            let name = self.entry_name(&entry.name);
+
+            if let Some(format) = archives::format_for(&entry.name) {
+                let path = self.entry_path(&name);
+                match format.entries(Box::new(Cursor::new(entry.contents)), &entry.name, &path) {
+                    Ok(entries) => self.archives.push((Some(name), entries)),
+                    Err(error) => {
+                        return Some(Err(SkippedFile {
+                            origin: self.origin(Some(name)),
+                            error,
+                        }))
+                    }
+                }
+                continue;
+            }
 
-    /// Extrae los archivos xml de un archivo zip
-    pub fn extract_from_zip<P: AsRef<Path>>(path: P) -> Result<Vec<String>, String> {
-        let mut archive = ZipArchive::try_from_path(path)?;
-        let xmls = archive.extract_xml_files()?;
+            let source = match String::from_utf8(entry.contents) {
+                Ok(contents) => Ok(XmlSource {
+                    origin: self.origin(Some(name)),
+                    contents,
+                }),
+                Err(e) => Err(SkippedFile {
+                    error: SatrError::Io(
+                        self.entry_path(&name),
+                        io::Error::new(io::ErrorKind::InvalidData, e),
+                    ),
+                    origin: self.origin(Some(name)),
+                }),
+            };
 
-        Ok(xmls)
+            return Some(source);
+        }
     }
 }
 
-struct ZipArchive {
-    pub archive: zip::ZipArchive<BufReader<File>>,
+/// Contenido de un archivo xml y su ubicación.
+#[derive(Debug, Clone)]
+pub struct XmlSource {
+    pub origin: Origin,
+    pub contents: String,
+}
+
+impl XmlSource {
+    #[inline]
+    pub fn root(&self) -> RootElement {
+        RootElement::read(&self.contents)
+    }
 }
 
-impl ZipArchive {
-    pub fn try_from_path<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-        let file = File::open(path).map_err(|e| e.to_string())?;
-        let reader = BufReader::new(file);
+/// Tipo de documento según el elemento raíz del xml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    /// `cfdi:Comprobante`.
+    Cfdi,
 
-        let zip_files = zip::ZipArchive::new(reader).map_err(|e| e.to_string())?;
+    /// `retenciones:Retenciones`.
+    Retention,
 
-        Ok(ZipArchive { archive: zip_files })
-    }
+    /// Cualquier otro elemento raíz, o un xml que no pudo leerse.
+    Other,
+}
 
-    pub fn extract_xml_files(&mut self) -> Result<Vec<String>, String> {
-        let mut xmls = Vec::new();
+/// Elemento raíz de un xml.
+pub struct RootElement {
+    pub kind: DocumentKind,
+}
 
-        for i in 0..self.archive.len() {
-            let mut file = self.archive.by_index(i).map_err(|e| e.to_string())?;
-            let file_name = file.name();
+impl RootElement {
+    pub fn read(xml: &str) -> Self {
+        let mut reader = Reader::from_str(xml);
 
-            if file_name.ends_with(".xml") {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml).map_err(|e| e.to_string())?;
-                xmls.push(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                    let kind = match e.local_name().as_ref() {
+                        b"Comprobante" => DocumentKind::Cfdi,
+                        b"Retenciones" => DocumentKind::Retention,
+                        _ => DocumentKind::Other,
+                    };
+
+                    return RootElement { kind };
+                }
+                Ok(Event::Eof) | Err(_) => {
+                    return RootElement {
+                        kind: DocumentKind::Other,
+                    }
+                }
+                _ => {}
             }
         }
+    }
+}
+
+/// Ubicación de un archivo xml.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// Archivo en disco.
+    File(PathBuf),
 
-        Ok(xmls)
+    /// Archivo dentro de un archivo comprimido. En los archivos anidados, `entry` incluye
+    /// las entradas que lo contienen separadas por `:`.
+    Archive { path: PathBuf, entry: String },
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Archive { path, entry } => write!(f, "{}:{entry}", path.display()),
+        }
     }
 }
@@ -0,0 +1,273 @@
+use std::{
+    io::{self, Read, Seek},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::error::SatrError;
+
+use super::{
+    mail::{self, Eml, Mbox},
+    XmlExtractor,
+};
+
+/// Formatos soportados, en el orden en que se prueban.
+pub static FORMATS: &[&dyn ArchiveFormat] = &[&Zip, &TarGz, &Tar, &Gzip, &Eml, &Mbox];
+
+/// Lector de un archivo en disco o del contenido de una entrada de otro archivo.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Entradas de un archivo que son xml u otro formato soportado, ver
+/// [`XmlExtractor::is_supported`]. Cada entrada se descomprime hasta que se solicita.
+pub type Entries = Box<dyn Iterator<Item = Result<ArchiveEntry, SatrError>>>;
+
+/// Formato de archivo que contiene otros archivos. Un formato nuevo solo necesita
+/// implementar este trait y agregarse a [`FORMATS`].
+pub trait ArchiveFormat: Sync {
+    /// Indica si el nombre del archivo corresponde al formato, sin distinguir mayúsculas.
+    fn matches(&self, name: &str) -> bool;
+
+    /// Recorre las entradas del archivo. `name` es el nombre del archivo o de la entrada
+    /// que lo contiene, y `path` solo se usa en los errores.
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError>;
+}
+
+/// Indica si el nombre termina con la extensión indicada, sin distinguir mayúsculas, ya que
+/// algunos PAC envían los adjuntos como `FACTURA.XML`.
+pub fn has_extension(name: &str, extension: &str) -> bool {
+    name.len() >= extension.len()
+        && name.as_bytes()[name.len() - extension.len()..]
+            .eq_ignore_ascii_case(extension.as_bytes())
+}
+
+/// Formato que corresponde al nombre de un archivo.
+pub fn format_for(name: &str) -> Option<&'static dyn ArchiveFormat> {
+    FORMATS.iter().copied().find(|f| f.matches(name))
+}
+
+/// Formato de un archivo en disco. Además de los que corresponden por nombre, los correos
+/// de una carpeta Maildir no tienen extensión y se leen como `.eml`.
+pub fn format_for_file(path: &Path) -> Option<&'static dyn ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy();
+
+    format_for(&name).or_else(|| mail::is_maildir_message(path).then_some(&Eml as _))
+}
+
+/// Ruta con la que se reportan los errores de una entrada: el archivo y la entrada
+/// separados por `:`, igual que [`Origin`](super::Origin).
+pub fn entry_path(path: &Path, entry: &str) -> PathBuf {
+    PathBuf::from(format!("{}:{entry}", path.display()))
+}
+
+/// Archivo dentro de otro.
+pub struct ArchiveEntry {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+pub struct Zip;
+
+impl ArchiveFormat for Zip {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".zip")
+    }
+
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        _name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        let archive =
+            zip::ZipArchive::new(reader).map_err(|e| SatrError::Zip(path.to_path_buf(), e))?;
+
+        Ok(Box::new(ZipEntries {
+            path: path.to_path_buf(),
+            archive,
+            index: 0,
+        }))
+    }
+}
+
+struct ZipEntries {
+    path: PathBuf,
+    archive: zip::ZipArchive<Box<dyn ReadSeek>>,
+
+    /// Siguiente entrada a revisar.
+    index: usize,
+}
+
+impl ZipEntries {
+    fn read_entry(&mut self, index: usize) -> Result<Option<ArchiveEntry>, SatrError> {
+        let mut file = self
+            .archive
+            .by_index(index)
+            .map_err(|e| SatrError::Zip(self.path.clone(), e))?;
+        let name = file.name().to_string();
+
+        if !XmlExtractor::is_supported(&name) {
+            return Ok(None);
+        }
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(|e| SatrError::Io(entry_path(&self.path, &name), e))?;
+
+        Ok(Some(ArchiveEntry { name, contents }))
+    }
+}
+
+impl Iterator for ZipEntries {
+    type Item = Result<ArchiveEntry, SatrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.archive.len() {
+            let index = self.index;
+            self.index += 1;
+
+            if let Some(entry) = self.read_entry(index).transpose() {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct Tar;
+
+impl ArchiveFormat for Tar {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".tar")
+    }
+
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        _name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        tar_entries(reader, path)
+    }
+}
+
+pub struct TarGz;
+
+impl ArchiveFormat for TarGz {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".tar.gz") || has_extension(name, ".tgz")
+    }
+
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        _name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        tar_entries(MultiGzDecoder::new(reader), path)
+    }
+}
+
+/// Entradas soportadas de un tar. Las entradas de un tar solo pueden recorrerse en orden
+/// mientras se tiene prestado el archivo, por lo que se recorren en otro hilo que lee la
+/// siguiente entrada y espera a que se solicite; si el tar está dañado se devuelven las
+/// entradas anteriores seguidas del error.
+fn tar_entries<R: Read + Send + 'static>(reader: R, path: &Path) -> Result<Entries, SatrError> {
+    let path = path.to_path_buf();
+    let (sender, receiver) = mpsc::sync_channel(0);
+
+    thread::spawn(move || {
+        let io_error = |e| SatrError::Io(path.clone(), e);
+        let mut archive = tar::Archive::new(reader);
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                sender.send(Err(io_error(e))).ok();
+                return;
+            }
+        };
+
+        for entry in entries {
+            let entry = match entry.and_then(read_tar_entry) {
+                Ok(Some(entry)) => Ok(entry),
+                Ok(None) => continue,
+                Err(e) => Err(io_error(e)),
+            };
+            let failed = entry.is_err();
+
+            // Si ya no se solicitan más entradas, se deja de leer el tar.
+            if sender.send(entry).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    Ok(Box::new(receiver.into_iter()))
+}
+
+/// Lee una entrada de un tar si es un archivo soportado.
+fn read_tar_entry<R: Read>(mut entry: tar::Entry<'_, R>) -> io::Result<Option<ArchiveEntry>> {
+    let name = entry.path()?.to_string_lossy().to_string();
+    let entry_type = entry.header().entry_type();
+    if !(entry_type.is_file() || entry_type.is_contiguous()) || !XmlExtractor::is_supported(&name) {
+        return Ok(None);
+    }
+
+    // Si el tar termina antes que la entrada, la lectura no falla por sí sola.
+    let mut contents = Vec::new();
+    entry.read_to_end(&mut contents)?;
+    if (contents.len() as u64) < entry.size() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(Some(ArchiveEntry { name, contents }))
+}
+
+/// Un solo archivo comprimido con gzip, por ejemplo `factura.xml.gz`.
+pub struct Gzip;
+
+impl ArchiveFormat for Gzip {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".gz")
+    }
+
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        let name = Path::new(name)
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        let name = if has_extension(&name, ".gz") {
+            name[..name.len() - ".gz".len()].to_string()
+        } else {
+            name.to_string()
+        };
+        if !XmlExtractor::is_supported(&name) {
+            return Ok(Box::new(std::iter::empty()));
+        }
+
+        let mut contents = Vec::new();
+        MultiGzDecoder::new(reader)
+            .read_to_end(&mut contents)
+            .map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+
+        Ok(Box::new(std::iter::once(Ok(ArchiveEntry {
+            name,
+            contents,
+        }))))
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use mail_parser::{mailbox::mbox, Message, MessageParser, MimeHeaders};
+
+use crate::error::SatrError;
+
+use super::{
+    archives::{has_extension, ArchiveEntry, ArchiveFormat, Entries, ReadSeek},
+    XmlExtractor,
+};
+
+/// Correo en formato MIME. Sus entradas son los archivos adjuntos, incluyendo los de los
+/// correos reenviados como adjunto.
+pub struct Eml;
+
+impl ArchiveFormat for Eml {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".eml")
+    }
+
+    fn entries(
+        &self,
+        mut reader: Box<dyn ReadSeek>,
+        _name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .map_err(|e| SatrError::Io(path.to_path_buf(), e))?;
+
+        let message = MessageParser::default().parse(&contents).ok_or_else(|| {
+            SatrError::Io(
+                path.to_path_buf(),
+                io::Error::new(io::ErrorKind::InvalidData, "No es un correo válido"),
+            )
+        })?;
+
+        let mut attachments = Vec::new();
+        collect_attachments(&message, &mut attachments);
+
+        Ok(Box::new(attachments.into_iter().map(Ok)))
+    }
+}
+
+fn collect_attachments(message: &Message, attachments: &mut Vec<ArchiveEntry>) {
+    for part in message.attachments() {
+        if let Some(forwarded) = part.message() {
+            collect_attachments(forwarded, attachments);
+            continue;
+        }
+
+        match part.attachment_name() {
+            Some(name) if XmlExtractor::is_supported(name) => attachments.push(ArchiveEntry {
+                name: name.to_string(),
+                contents: part.contents().to_vec(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Buzón mbox. Cada correo es una entrada `<n>.eml`, numerada desde 1, que se lee hasta
+/// que se solicita.
+pub struct Mbox;
+
+impl ArchiveFormat for Mbox {
+    fn matches(&self, name: &str) -> bool {
+        has_extension(name, ".mbox")
+    }
+
+    fn entries(
+        &self,
+        reader: Box<dyn ReadSeek>,
+        _name: &str,
+        path: &Path,
+    ) -> Result<Entries, SatrError> {
+        let path = path.to_path_buf();
+        let mut failed = false;
+        // Después de un error de lectura el buzón ya no puede recorrerse.
+        let messages = mbox::MessageIterator::new(BufReader::new(reader))
+            .enumerate()
+            .map_while(move |(i, message)| match message {
+                _ if failed => None,
+                Ok(message) => Some(Ok(ArchiveEntry {
+                    name: format!("{}.eml", i + 1),
+                    contents: message.unwrap_contents(),
+                })),
+                Err(e) => {
+                    failed = true;
+                    Some(Err(SatrError::Io(path.clone(), e)))
+                }
+            });
+
+        Ok(Box::new(messages))
+    }
+}
+
+/// Indica si un archivo es un correo de una carpeta Maildir, es decir, si está en el
+/// subdirectorio `cur` o `new` de una carpeta que tiene ambos.
+pub fn is_maildir_message(path: &Path) -> bool {
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    let Some(maildir) = parent.parent() else {
+        return false;
+    };
+
+    parent
+        .file_name()
+        .is_some_and(|name| name == "cur" || name == "new")
+        && ["cur", "new"].iter().all(|dir| maildir.join(dir).is_dir())
+}